@@ -0,0 +1,139 @@
+use std::fs;
+use std::time::{Duration, Instant};
+
+use tui::{backend::TestBackend, Terminal};
+
+use rust_commandline_example::category::{Category, DEFAULT_CATEGORIES};
+use rust_commandline_example::config::{Config, CONFIG_PATH};
+use rust_commandline_example::data_row::DataRow;
+use rust_commandline_example::db_stuff::{Database, Error, Store};
+use rust_commandline_example::generic_tui::{render, select_next, AppState, MenuItem};
+use rust_commandline_example::keymap::Keymap;
+use rust_commandline_example::migrations::CURRENT_VERSION;
+use rust_commandline_example::pet::Pet;
+
+/// The size of the terminal the frames are drawn for.
+const COLUMNS: u16 = 120;
+const LINES: u16 = 40;
+
+const USAGE: &str =
+    "usage: cargo run --release --example bench -- [--pets <n,n,...>] [--frames <n>]";
+
+fn main() {
+    if let Err(e) =
+        parse_args(std::env::args().skip(1)).and_then(|(sizes, frames)| run(&sizes, frames))
+    {
+        eprintln!("error: {}", e);
+        std::process::exit(1);
+    }
+}
+
+/// The screens that are timed, by the name their column has.
+const SCREENS: [(&str, MenuItem); 4] = [
+    ("data", MenuItem::Data),
+    ("stats", MenuItem::Stats),
+    ("upcoming", MenuItem::Upcoming),
+    ("calendar", MenuItem::Calendar),
+];
+
+/// Draws `frames` frames of each of `SCREENS` for each number of random pets in `sizes`, and
+/// prints how long a frame took on average, in milliseconds. The frames go to a terminal in
+/// memory and the pets to a file in the temp directory, so neither the screen nor the data
+/// file are touched.
+fn run(sizes: &[usize], frames: usize) -> Result<(), Error> {
+    let config = Config::load(CONFIG_PATH)?;
+    let keymap = Keymap::new(&config.keys)?;
+    print!("{:>10}", "pets");
+    for (name, _) in &SCREENS {
+        print!("  {:>10}", name);
+    }
+    println!("  (ms per frame)");
+    for &size in sizes {
+        let mut state = random_pets(size, &config)?;
+        print!("{:>10}", size);
+        for &(_, screen) in &SCREENS {
+            state.active_menu_item = screen;
+            let frame_time = time_frames(&mut state, frames, &keymap)?;
+            print!("  {:>10.3}", frame_time.as_secs_f64() * 1000.0);
+        }
+        println!();
+    }
+    Ok(())
+}
+
+fn parse_args<I: Iterator<Item = String>>(mut args: I) -> Result<(Vec<usize>, usize), Error> {
+    let mut sizes = vec![1_000, 10_000, 100_000];
+    let mut frames = 200;
+    while let Some(arg) = args.next() {
+        let value = match arg.as_str() {
+            "--pets" | "--frames" => args
+                .next()
+                .ok_or_else(|| Error::Usage(format!("'{}' needs a value\n{}", arg, USAGE)))?,
+            other => {
+                return Err(Error::Usage(format!(
+                    "unexpected argument '{}'\n{}",
+                    other, USAGE
+                )))
+            }
+        };
+        if arg == "--pets" {
+            sizes = value
+                .split(',')
+                .map(|size| size.trim().parse())
+                .collect::<Result<_, _>>()
+                .map_err(|_| {
+                    Error::Usage(format!("'{}' is not a list of numbers\n{}", value, USAGE))
+                })?;
+        } else {
+            frames = value.parse().map_err(|_| {
+                Error::Usage(format!("'{}' is not a number of frames\n{}", value, USAGE))
+            })?;
+        }
+    }
+    Ok((sizes, frames))
+}
+
+/// The TUI for `size` random pets.
+fn random_pets(size: usize, config: &Config) -> Result<AppState<Pet>, Error> {
+    let path = std::env::temp_dir().join(format!("bench-{}-{}.json", std::process::id(), size));
+    let store = Store::open(&path, config.backups.clone())?;
+    let db = Database {
+        version: CURRENT_VERSION,
+        categories: DEFAULT_CATEGORIES
+            .iter()
+            .map(|name| Category::new(name))
+            .collect::<Result<_, _>>()?,
+        pets: (0..size).map(|_| Pet::create_placeholder()).collect(),
+        children: Vec::new(),
+    };
+    store.write(&db)?;
+    let state = AppState::<Pet>::load(&store);
+    fs::remove_file(&path)?;
+    state
+}
+
+/// How long a frame of the active screen of `state` takes on average, over `frames` frames.
+/// Between frames the Pets and Calendar screens move their selection, the others are drawn
+/// again as they are for a tick.
+fn time_frames(
+    state: &mut AppState<Pet>,
+    frames: usize,
+    keymap: &Keymap,
+) -> Result<Duration, Error> {
+    let mut terminal = Terminal::new(TestBackend::new(COLUMNS, LINES))?;
+    let draw = |terminal: &mut Terminal<TestBackend>, state: &mut AppState<Pet>| {
+        render(terminal, keymap, state).map_err(|e| Error::Invalid(e.to_string()))
+    };
+    // The first frame draws everything, the rest only what changed; leave it out.
+    draw(&mut terminal, state)?;
+    let started = Instant::now();
+    for _ in 0..frames {
+        match state.active_menu_item {
+            MenuItem::Data => select_next(&mut state.data_list_state, state.data.len()),
+            MenuItem::Calendar => state.calendar.move_days(1),
+            _ => {}
+        }
+        draw(&mut terminal, state)?;
+    }
+    Ok(started.elapsed() / frames.max(1) as u32)
+}
@@ -0,0 +1,280 @@
+use std::collections::BTreeMap;
+use std::fs::{self, OpenOptions};
+use std::io::{self, BufRead, BufReader, Write};
+use std::path::PathBuf;
+
+use chrono::prelude::*;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::backups::changed_fields;
+use crate::child::Child;
+use crate::db_stuff::{Database, Entity, Error, Store, Stored};
+use crate::migrations;
+use crate::pet::Pet;
+
+#[derive(Serialize, Deserialize, Copy, Clone, Debug, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum Action {
+    Insert,
+    Update,
+    Delete,
+}
+
+impl std::fmt::Display for Action {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.pad(match self {
+            Action::Insert => "insert",
+            Action::Update => "update",
+            Action::Delete => "delete",
+        })
+    }
+}
+
+/// One line of the audit log: a single row as it was before and after a change.
+#[derive(Serialize, Deserialize, Clone)]
+pub struct AuditEntry {
+    pub at: DateTime<Utc>,
+    pub user: String,
+    pub entity: Entity,
+    pub record_id: usize,
+    pub action: Action,
+    #[serde(default)]
+    pub before: Option<Value>,
+    #[serde(default)]
+    pub after: Option<Value>,
+}
+
+impl AuditEntry {
+    /// A one-line summary, without the timestamp and user.
+    pub fn describe(&self) -> String {
+        match self.action {
+            Action::Insert => format!("{} {} created", self.entity, self.record_id),
+            Action::Delete => format!("{} {} deleted for good", self.entity, self.record_id),
+            Action::Update => {
+                let before = self.before.as_ref().unwrap_or(&Value::Null);
+                let after = self.after.as_ref().unwrap_or(&Value::Null);
+                format!(
+                    "{} {}: {}",
+                    self.entity,
+                    self.record_id,
+                    changed_fields(before, after).join(", ")
+                )
+            }
+        }
+    }
+}
+
+/// The log sits next to the data file, `data/db.json` logs to `data/db.json.audit.jsonl`, so
+/// `data/db.json` and `data/db.yaml` keep logs of their own.
+pub fn log_path(store: &Store) -> PathBuf {
+    let mut name = store.path.file_name().unwrap_or_default().to_os_string();
+    name.push(".audit.jsonl");
+    store.path.with_file_name(name)
+}
+
+fn current_user() -> String {
+    std::env::var("USER")
+        .or_else(|_| std::env::var("LOGNAME"))
+        .or_else(|_| std::env::var("USERNAME"))
+        .unwrap_or_else(|_| "unknown".to_owned())
+}
+
+fn append(store: &Store, entries: &[AuditEntry]) -> Result<(), Error> {
+    if entries.is_empty() {
+        return Ok(());
+    }
+    let mut file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(log_path(store))?;
+    let mut lines = String::new();
+    for entry in entries {
+        lines.push_str(&serde_json::to_string(entry)?);
+        lines.push('\n');
+    }
+    file.write_all(lines.as_bytes())?;
+    Ok(())
+}
+
+fn entry<T: Stored>(
+    action: Action,
+    before: Option<&T>,
+    after: Option<&T>,
+) -> Result<AuditEntry, Error> {
+    let record_id = before.or(after).map_or(0, |row| row.id());
+    Ok(AuditEntry {
+        at: Utc::now(),
+        user: current_user(),
+        entity: T::entity(),
+        record_id,
+        action,
+        before: before.map(serde_json::to_value).transpose()?,
+        after: after.map(serde_json::to_value).transpose()?,
+    })
+}
+
+/// Logs a change to a single row. `before` is `None` for inserts, `after` is `None` for deletes.
+pub fn record<T: Stored>(
+    store: &Store,
+    action: Action,
+    before: Option<&T>,
+    after: Option<&T>,
+) -> Result<(), Error> {
+    append(store, &[entry(action, before, after)?])
+}
+
+/// Logs every row that differs between `old` and `new`, for bulk changes like imports and restores.
+pub fn record_changes(store: &Store, old: &Database, new: &Database) -> Result<(), Error> {
+    let mut entries = changes::<Pet>(old, new)?;
+    entries.extend(changes::<Child>(old, new)?);
+    append(store, &entries)
+}
+
+fn changes<T: Stored>(old: &Database, new: &Database) -> Result<Vec<AuditEntry>, Error> {
+    let old_rows: BTreeMap<usize, &T> = T::rows(old).iter().map(|r| (r.id(), r)).collect();
+    let new_rows: BTreeMap<usize, &T> = T::rows(new).iter().map(|r| (r.id(), r)).collect();
+    let mut entries = Vec::new();
+    for (id, old_row) in &old_rows {
+        match new_rows.get(id) {
+            None => entries.push(entry(Action::Delete, Some(*old_row), None)?),
+            Some(new_row) => {
+                if serde_json::to_value(old_row)? != serde_json::to_value(new_row)? {
+                    entries.push(entry(Action::Update, Some(*old_row), Some(*new_row))?);
+                }
+            }
+        }
+    }
+    for (id, new_row) in &new_rows {
+        if !old_rows.contains_key(id) {
+            entries.push(entry::<T>(Action::Insert, None, Some(*new_row))?);
+        }
+    }
+    Ok(entries)
+}
+
+/// Data that has been written, and how logging the change went. The change stands even when
+/// it couldn't be logged, so that is reported on its own.
+pub struct Logged {
+    pub db: Database,
+    pub log: Result<(), Error>,
+}
+
+impl Logged {
+    /// Data that was left as it was, with nothing to log.
+    pub fn unchanged(db: Database) -> Self {
+        Logged { db, log: Ok(()) }
+    }
+}
+
+/// What could be read of the log.
+#[derive(Default)]
+pub struct Log {
+    pub entries: Vec<AuditEntry>,
+    /// Lines that aren't a logged change, like one cut short by a crash; they are left out.
+    pub skipped: usize,
+}
+
+/// Every logged change, oldest first. A missing log just means nothing was logged yet.
+pub fn read_log(store: &Store) -> Result<Log, Error> {
+    let file = match fs::File::open(log_path(store)) {
+        Ok(file) => file,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Log::default()),
+        Err(e) => return Err(e.into()),
+    };
+    let mut log = Log::default();
+    for line in BufReader::new(file).lines() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        match serde_json::from_str(&line) {
+            Ok(entry) => log.entries.push(entry),
+            Err(_) => log.skipped += 1,
+        }
+    }
+    Ok(log)
+}
+
+/// The change timeline of one record, oldest first. `entity` of `None` matches both kinds.
+pub fn history(store: &Store, entity: Option<Entity>, id: usize) -> Result<Log, Error> {
+    let mut log = read_log(store)?;
+    log.entries
+        .retain(|e| e.record_id == id && entity.is_none_or(|kind| kind == e.entity));
+    Ok(log)
+}
+
+/// A state a record was in, and since when (`None` if that's unknown).
+pub type Version<T> = (Option<DateTime<Utc>>, T);
+
+/// The states a record has been in according to the log, oldest first, each with the time it
+/// came to be (`None` for the state it was in before it was first logged). The row as it is
+/// now always comes last.
+pub fn versions<T: Stored>(
+    store: &Store,
+    current: &T,
+    up_to: Option<DateTime<Utc>>,
+) -> Result<Vec<Version<T>>, Error> {
+    let mut versions = Vec::new();
+    for entry in history(store, Some(T::entity()), current.id())?.entries {
+        if up_to.is_some_and(|at| entry.at > at) {
+            break;
+        }
+        if versions.is_empty() {
+            if let Some(before) = entry.before.and_then(|v| logged_row(v).ok()) {
+                versions.push((None, before));
+            }
+        }
+        if let Some(after) = entry.after.and_then(|v| logged_row(v).ok()) {
+            versions.push((Some(entry.at), after));
+        }
+    }
+    let current_value = serde_json::to_value(current)?;
+    let last_matches = match versions.last() {
+        Some((_, last)) => serde_json::to_value(last)? == current_value,
+        None => false,
+    };
+    if !last_matches {
+        versions.push((None, current.clone()));
+    }
+    Ok(versions)
+}
+
+/// Rebuilds the data as it was at `at`, by undoing every logged change made after it.
+pub fn as_of(store: &Store, current: &Database, at: DateTime<Utc>) -> Result<Database, Error> {
+    let mut past = current.clone();
+    let mut later: Vec<AuditEntry> = read_log(store)?
+        .entries
+        .into_iter()
+        .filter(|e| e.at > at)
+        .collect();
+    later.reverse();
+    for entry in later {
+        match entry.entity {
+            Entity::Pets => undo::<Pet>(&mut past, &entry)?,
+            Entity::Children => undo::<Child>(&mut past, &entry)?,
+        }
+    }
+    Ok(past)
+}
+
+fn undo<T: Stored>(db: &mut Database, entry: &AuditEntry) -> Result<(), Error> {
+    let before: Option<T> = entry.before.clone().map(logged_row).transpose()?;
+    let position = T::position(db, entry.record_id);
+    let rows = T::rows_mut(db);
+    match (position, before) {
+        (Some(idx), Some(before)) => rows[idx] = before,
+        (Some(idx), None) => {
+            rows.remove(idx);
+        }
+        (None, Some(before)) => rows.push(before),
+        (None, None) => {}
+    }
+    Ok(())
+}
+
+/// Reads a row snapshot from the log, bringing it up to date if it was logged by an older version.
+fn logged_row<T: Stored>(mut value: Value) -> Result<T, Error> {
+    migrations::backfill_birthdate(&mut value)?;
+    Ok(serde_json::from_value(value)?)
+}
@@ -0,0 +1,309 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::path::PathBuf;
+
+use chrono::prelude::*;
+use serde::Serialize;
+use serde_json::{Map, Value};
+
+use crate::audit::{self, Logged};
+use crate::child::Child;
+use crate::config::BackupConfig;
+use crate::db_stuff::{Database, Error, Store, Stored};
+use crate::pet::Pet;
+
+const BACKUP_DIR: &str = "backups";
+const ROLLING_FORMAT: &str = "%Y%m%dT%H%M%S%.3fZ";
+const DAILY_FORMAT: &str = "%Y-%m-%d";
+const DAILY_MARKER: &str = "daily-";
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum BackupKind {
+    Rolling,
+    Daily,
+}
+
+/// A copy of the data file. `label` is the timestamp part of the file name,
+/// which is also what `backups restore` expects.
+#[derive(Clone)]
+pub struct Backup {
+    pub path: PathBuf,
+    pub label: String,
+    pub kind: BackupKind,
+    pub taken_at: DateTime<Utc>,
+}
+
+impl Backup {
+    /// Backups are plain copies of the data file, so older ones get migrated like it would.
+    pub fn read(&self) -> Result<Database, Error> {
+        Store::open(&self.path, BackupConfig::default())?.read()
+    }
+}
+
+fn backup_dir(store: &Store) -> PathBuf {
+    store
+        .path
+        .parent()
+        .map_or_else(|| PathBuf::from(BACKUP_DIR), |p| p.join(BACKUP_DIR))
+}
+
+fn file_stem(store: &Store) -> String {
+    store
+        .path
+        .file_stem()
+        .map_or_else(|| "db".to_owned(), |s| s.to_string_lossy().into_owned())
+}
+
+fn extension(store: &Store) -> String {
+    store
+        .path
+        .extension()
+        .map_or_else(String::new, |e| e.to_string_lossy().into_owned())
+}
+
+fn parse_label(label: &str) -> Option<(BackupKind, DateTime<Utc>)> {
+    if let Some(day) = label.strip_prefix(DAILY_MARKER) {
+        let date = NaiveDate::parse_from_str(day, DAILY_FORMAT).ok()?;
+        return Some((
+            BackupKind::Daily,
+            Utc.from_utc_datetime(&date.and_hms_opt(0, 0, 0)?),
+        ));
+    }
+    let naive = NaiveDateTime::parse_from_str(label, ROLLING_FORMAT).ok()?;
+    Some((BackupKind::Rolling, Utc.from_utc_datetime(&naive)))
+}
+
+/// Every backup of this store, newest first.
+pub fn list(store: &Store) -> Result<Vec<Backup>, Error> {
+    let dir = backup_dir(store);
+    if !dir.exists() {
+        return Ok(Vec::new());
+    }
+    let prefix = file_stem(store) + "-";
+    let suffix = format!(".{}", extension(store));
+    let mut backups = Vec::new();
+    for entry in fs::read_dir(&dir)? {
+        let path = entry?.path();
+        let name = match path.file_name().and_then(|n| n.to_str()) {
+            Some(name) => name.to_owned(),
+            None => continue,
+        };
+        let label = match name
+            .strip_prefix(&prefix)
+            .and_then(|rest| rest.strip_suffix(&suffix))
+        {
+            Some(label) => label.to_owned(),
+            None => continue,
+        };
+        if let Some((kind, taken_at)) = parse_label(&label) {
+            backups.push(Backup {
+                path,
+                label,
+                kind,
+                taken_at,
+            });
+        }
+    }
+    backups.sort_by(|a, b| b.taken_at.cmp(&a.taken_at).then(a.label.cmp(&b.label)));
+    Ok(backups)
+}
+
+/// Copies the current data file into the backup directory, plus a daily snapshot if there is
+/// none for today yet, then drops the oldest backups beyond what the config asks to keep.
+pub fn take(store: &Store) -> Result<Option<PathBuf>, Error> {
+    if !store.path.exists() {
+        return Ok(None);
+    }
+    let dir = backup_dir(store);
+    fs::create_dir_all(&dir)?;
+    let now = Utc::now();
+    let stem = file_stem(store);
+    let ext = extension(store);
+
+    let rolling = dir.join(format!("{}-{}.{}", stem, now.format(ROLLING_FORMAT), ext));
+    fs::copy(&store.path, &rolling)?;
+    let daily = dir.join(format!(
+        "{}-{}{}.{}",
+        stem,
+        DAILY_MARKER,
+        now.format(DAILY_FORMAT),
+        ext
+    ));
+    if !daily.exists() {
+        fs::copy(&store.path, &daily)?;
+    }
+
+    let backups = list(store)?;
+    for (kind, keep) in &[
+        (BackupKind::Rolling, store.backups.keep),
+        (BackupKind::Daily, store.backups.daily),
+    ] {
+        for old in backups.iter().filter(|b| b.kind == *kind).skip(*keep) {
+            fs::remove_file(&old.path)?;
+        }
+    }
+    Ok(Some(rolling))
+}
+
+/// Finds the backup whose label starts with `label`, which has to pick out exactly one.
+pub fn find(store: &Store, label: &str) -> Result<Backup, Error> {
+    let mut matching: Vec<Backup> = list(store)?
+        .into_iter()
+        .filter(|b| b.label.starts_with(label))
+        .collect();
+    match matching.len() {
+        1 => Ok(matching.remove(0)),
+        0 => Err(Error::Usage(format!("no backup matches '{}'", label))),
+        n => Err(Error::Usage(format!(
+            "{} backups match '{}', give more of the timestamp",
+            n, label
+        ))),
+    }
+}
+
+/// Replaces the current data with the backup's. The current data gets backed up first,
+/// so a restore can itself be undone.
+pub fn restore(store: &Store, backup: &Backup) -> Result<Logged, Error> {
+    let current = store.read()?;
+    let db = backup.read()?;
+    store.write_after_backup(&db)?;
+    let log = audit::record_changes(store, &current, &db);
+    Ok(Logged { db, log })
+}
+
+/// One line per added, removed or changed row, going from `old` to `new`.
+pub fn diff(old: &Database, new: &Database) -> Vec<String> {
+    let mut lines = diff_rows::<Pet>(old, new);
+    lines.extend(diff_rows::<Child>(old, new));
+    lines
+}
+
+fn by_id<T: Stored>(db: &Database) -> BTreeMap<usize, &T> {
+    T::rows(db).iter().map(|row| (row.id(), row)).collect()
+}
+
+fn to_value<T: Serialize>(row: &T) -> Value {
+    serde_json::to_value(row).unwrap_or(Value::Null)
+}
+
+fn diff_rows<T: Stored>(old: &Database, new: &Database) -> Vec<String> {
+    let old_rows = by_id::<T>(old);
+    let new_rows = by_id::<T>(new);
+    let mut lines = Vec::new();
+    for (id, old_row) in &old_rows {
+        match new_rows.get(id) {
+            None => lines.push(format!("- {} {} {}", T::row_name(), id, old_row.name())),
+            Some(new_row) => {
+                let changes = changed_fields(&to_value(*old_row), &to_value(*new_row));
+                if !changes.is_empty() {
+                    lines.push(format!(
+                        "~ {} {} {}: {}",
+                        T::row_name(),
+                        id,
+                        new_row.name(),
+                        changes.join(", ")
+                    ));
+                }
+            }
+        }
+    }
+    for (id, new_row) in &new_rows {
+        if !old_rows.contains_key(id) {
+            lines.push(format!("+ {} {} {}", T::row_name(), id, new_row.name()));
+        }
+    }
+    lines
+}
+
+/// `field old -> new` for every top-level key whose value differs between the two objects.
+pub fn changed_fields(old: &Value, new: &Value) -> Vec<String> {
+    let empty = Map::new();
+    let old = old.as_object().unwrap_or(&empty);
+    let new = new.as_object().unwrap_or(&empty);
+    let keys: BTreeSet<&String> = old.keys().chain(new.keys()).collect();
+    keys.into_iter()
+        .filter(|k| old.get(*k) != new.get(*k))
+        .map(|k| {
+            format!(
+                "{} {} -> {}",
+                k,
+                old.get(k).unwrap_or(&Value::Null),
+                new.get(k).unwrap_or(&Value::Null)
+            )
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A store in a fresh temp directory, with a data file and these backups already taken.
+    fn store_with(name: &str, labels: &[&str], backups: BackupConfig) -> Store {
+        let dir = std::env::temp_dir().join(format!("backups-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(dir.join(BACKUP_DIR)).unwrap();
+        for label in labels {
+            fs::write(
+                dir.join(BACKUP_DIR).join(format!("db-{}.json", label)),
+                "[]",
+            )
+            .unwrap();
+        }
+        fs::write(dir.join("db.json"), "[]").unwrap();
+        Store::open(dir.join("db.json"), backups).unwrap()
+    }
+
+    fn labels(store: &Store, kind: BackupKind) -> Vec<String> {
+        let backups = list(store).unwrap().into_iter();
+        backups
+            .filter(|b| b.kind == kind)
+            .map(|b| b.label)
+            .collect()
+    }
+
+    #[test]
+    fn keeps_only_the_newest_backups_of_each_kind() {
+        let store = store_with(
+            "rotation",
+            &[
+                "20200101T000000.000Z",
+                "20200102T000000.000Z",
+                "20200103T000000.000Z",
+                "daily-2020-01-01",
+                "daily-2020-01-02",
+                "not-a-backup",
+            ],
+            BackupConfig { keep: 2, daily: 2 },
+        );
+        let taken = take(&store).unwrap().unwrap();
+
+        let rolling = labels(&store, BackupKind::Rolling);
+        assert_eq!(rolling.len(), 2);
+        assert!(taken.ends_with(format!("db-{}.json", rolling[0])));
+        assert_eq!(rolling[1], "20200103T000000.000Z");
+        let today = Utc::now().format(DAILY_FORMAT).to_string();
+        assert_eq!(
+            labels(&store, BackupKind::Daily),
+            [format!("daily-{}", today), "daily-2020-01-02".to_owned()]
+        );
+        assert!(backup_dir(&store).join("db-not-a-backup.json").exists());
+        fs::remove_dir_all(store.path.parent().unwrap()).unwrap();
+    }
+
+    #[test]
+    fn finds_backups_by_a_unique_label_prefix() {
+        let store = store_with(
+            "find",
+            &["20200101T000000.000Z", "20200102T000000.000Z"],
+            BackupConfig::default(),
+        );
+        assert_eq!(
+            find(&store, "20200102").unwrap().label,
+            "20200102T000000.000Z"
+        );
+        assert!(find(&store, "2020").is_err());
+        assert!(find(&store, "2019").is_err());
+        fs::remove_dir_all(store.path.parent().unwrap()).unwrap();
+    }
+}
@@ -0,0 +1,207 @@
+use std::convert::TryFrom;
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+
+use crate::db_stuff::{Database, Error};
+
+/// The kind of animal a pet is. Only names listed in `Database::categories` are valid.
+/// Loading one goes through `Category::new` too.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[serde(try_from = "String", into = "String")]
+pub struct Category(String);
+
+impl Category {
+    /// Category names are trimmed and lower-cased, so "Cats " and "cats" are the same category.
+    pub fn new(name: &str) -> Result<Self, Error> {
+        let name = name.trim().to_lowercase();
+        if name.is_empty() {
+            return Err(Error::Invalid("a category needs a name".to_owned()));
+        }
+        Ok(Category(name))
+    }
+}
+
+impl TryFrom<String> for Category {
+    type Error = Error;
+
+    fn try_from(name: String) -> Result<Self, Error> {
+        Category::new(&name)
+    }
+}
+
+impl From<Category> for String {
+    fn from(category: Category) -> String {
+        category.0
+    }
+}
+
+impl fmt::Display for Category {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+pub const DEFAULT_CATEGORIES: &[&str] = &["cats", "dogs"];
+
+impl Database {
+    pub fn has_category(&self, category: &Category) -> bool {
+        self.categories.contains(category)
+    }
+
+    /// How many pets, trashed ones included, are in `category`.
+    pub fn category_usage(&self, category: &Category) -> usize {
+        self.pets.iter().filter(|p| &p.category == category).count()
+    }
+
+    pub fn add_category(&mut self, name: &str) -> Result<Category, Error> {
+        let category = Category::new(name)?;
+        if self.has_category(&category) {
+            return Err(Error::Invalid(format!("'{}' already exists", category)));
+        }
+        self.categories.push(category.clone());
+        self.categories.sort();
+        Ok(category)
+    }
+
+    /// Renames a category, and every pet in it along with it.
+    pub fn rename_category(&mut self, from: &Category, to: &str) -> Result<Category, Error> {
+        let to = Category::new(to)?;
+        if &to == from {
+            return Ok(to);
+        }
+        if self.has_category(&to) {
+            return Err(Error::Invalid(format!(
+                "'{}' already exists, merge into it instead",
+                to
+            )));
+        }
+        let slot = self
+            .categories
+            .iter_mut()
+            .find(|c| *c == from)
+            .ok_or_else(|| Error::Invalid(format!("there is no category '{}'", from)))?;
+        *slot = to.clone();
+        self.categories.sort();
+        self.move_pets(from, &to);
+        Ok(to)
+    }
+
+    /// Moves every pet in `from` over to `into` and drops `from`.
+    pub fn merge_categories(&mut self, from: &Category, into: &Category) -> Result<(), Error> {
+        if from == into {
+            return Err(Error::Invalid(
+                "can't merge a category into itself".to_owned(),
+            ));
+        }
+        if !self.has_category(into) {
+            return Err(Error::Invalid(format!("there is no category '{}'", into)));
+        }
+        self.move_pets(from, into);
+        self.categories.retain(|c| c != from);
+        Ok(())
+    }
+
+    /// Only categories without pets can be deleted, the others have to be merged away.
+    pub fn delete_category(&mut self, category: &Category) -> Result<(), Error> {
+        match self.category_usage(category) {
+            0 => {
+                self.categories.retain(|c| c != category);
+                Ok(())
+            }
+            n => Err(Error::Invalid(format!(
+                "'{}' still has {} pet(s), merge it into another category instead",
+                category, n
+            ))),
+        }
+    }
+
+    fn move_pets(&mut self, from: &Category, to: &Category) {
+        for pet in self.pets.iter_mut().filter(|p| &p.category == from) {
+            pet.category = to.clone();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Two cats, one of them trashed, and a dog.
+    fn db() -> Database {
+        serde_json::from_value(serde_json::json!({
+            "version": crate::migrations::CURRENT_VERSION,
+            "categories": ["cats", "dogs"],
+            "pets": [
+                {"id": 1, "name": "Nacho", "category": "cats", "birthdate": "2018-08-20T12:00:00Z",
+                 "created_at": "2020-01-01T00:00:00Z"},
+                {"id": 2, "name": "Tacos", "category": "dogs", "birthdate": "2015-03-16T12:00:00Z",
+                 "created_at": "2020-01-01T00:00:00Z"},
+                {"id": 3, "name": "Mischka", "category": "cats", "birthdate": "2016-11-23T12:00:00Z",
+                 "created_at": "2020-01-01T00:00:00Z", "deleted_at": "2021-01-01T00:00:00Z"}
+            ],
+            "children": []
+        }))
+        .unwrap()
+    }
+
+    fn category(name: &str) -> Category {
+        Category::new(name).unwrap()
+    }
+
+    fn categories_of_pets(db: &Database) -> Vec<String> {
+        db.pets.iter().map(|pet| pet.category.to_string()).collect()
+    }
+
+    #[test]
+    fn tidies_names_and_refuses_empty_ones() {
+        assert_eq!(category(" Cats "), category("cats"));
+        assert!(Category::new("  ").is_err());
+        assert!(serde_json::from_value::<Category>(serde_json::json!("")).is_err());
+    }
+
+    #[test]
+    fn renames_trashed_pets_along() {
+        let mut db = db();
+        let felines = db.rename_category(&category("cats"), "Felines").unwrap();
+        assert_eq!(felines, category("felines"));
+        assert_eq!(db.categories, [category("dogs"), felines]);
+        assert_eq!(categories_of_pets(&db), ["felines", "dogs", "felines"]);
+        db.validate().unwrap();
+    }
+
+    #[test]
+    fn refuses_to_rename_onto_an_existing_category() {
+        let mut db = db();
+        assert!(db.rename_category(&category("cats"), "DOGS").is_err());
+        assert!(db.rename_category(&category("birds"), "parrots").is_err());
+        assert_eq!(categories_of_pets(&db), ["cats", "dogs", "cats"]);
+    }
+
+    #[test]
+    fn merges_pets_and_drops_the_old_category() {
+        let mut db = db();
+        assert!(db
+            .merge_categories(&category("cats"), &category("cats"))
+            .is_err());
+        assert!(db
+            .merge_categories(&category("cats"), &category("birds"))
+            .is_err());
+        db.merge_categories(&category("cats"), &category("dogs"))
+            .unwrap();
+        assert_eq!(db.categories, [category("dogs")]);
+        assert_eq!(categories_of_pets(&db), ["dogs", "dogs", "dogs"]);
+        db.validate().unwrap();
+    }
+
+    #[test]
+    fn only_deletes_categories_without_pets() {
+        let mut db = db();
+        db.add_category("birds").unwrap();
+        assert!(db.add_category("Birds").is_err());
+        assert_eq!(db.category_usage(&category("cats")), 2);
+        assert!(db.delete_category(&category("cats")).is_err());
+        db.delete_category(&category("birds")).unwrap();
+        assert_eq!(db.categories, [category("cats"), category("dogs")]);
+    }
+}
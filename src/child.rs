@@ -1,24 +1,42 @@
 use chrono::prelude::*;
 use rand::{distributions::Alphanumeric, prelude::*};
 use serde::{Deserialize, Serialize};
-use tui::{
-    layout::Constraint,
-    style::{Color, Modifier, Style},
-    text::{Span, Spans},
-    widgets::{Block, BorderType, Borders, Cell, Row, Table},
-};
 
-use crate::data_row::{Aged, DataRow};
+use crate::data_row::{local_time, AgeUnit, Born, DataRow, Field, FieldKind};
+use crate::db_stuff::Database;
 
 #[derive(Serialize, Deserialize, Clone)]
 pub struct Child {
     pub id: usize,
     pub name: String,
-    pub age: u8,
     pub birthdate: DateTime<Utc>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub deleted_at: Option<DateTime<Utc>>,
 }
 
+const CHILD_FIELDS: &[Field] = &[
+    Field {
+        name: "id",
+        label: "ID",
+        kind: FieldKind::Integer,
+    },
+    Field {
+        name: "name",
+        label: "Name",
+        kind: FieldKind::Text,
+    },
+    Field {
+        name: "birthdate",
+        label: "Birthdate",
+        kind: FieldKind::Timestamp,
+    },
+];
+
 impl DataRow for Child {
+    fn id(&self) -> usize {
+        self.id
+    }
+
     fn name(&self) -> String {
         self.name.clone()
     }
@@ -27,10 +45,6 @@ impl DataRow for Child {
         self.name = new_name.into();
     }
 
-    fn menu_titles() -> Vec<&'static str> {
-        vec!["Home", "Children", "Add", "Edit Name", "Delete", "Quit"]
-    }
-
     fn title() -> &'static str {
         "Children"
     }
@@ -43,77 +57,51 @@ impl DataRow for Child {
         "Child CLI".to_string()
     }
 
-    fn help_part<'a>() -> Vec<Spans<'a>> {
+    fn detail(&self, db: &Database, unit: AgeUnit) -> Vec<(&'static str, String)> {
+        let pets: Vec<String> = db.pets_of(self.id).iter().map(|pet| pet.name()).collect();
         vec![
-            Spans::from(vec![Span::raw(
-                "Press 'c' to access children, 'a' to add random new children,",
-            )]),
-            Spans::from(vec![Span::raw(
-                "'e' to edit the name of currently selected child",
-            )]),
-            Spans::from(vec![Span::raw(
-                "and 'd' to delete the currently selected child.",
-            )]),
+            ("ID", self.id.to_string()),
+            ("Name", self.name.clone()),
+            ("Age", self.age_text(unit)),
+            ("Birthdate", local_time(&self.birthdate)),
+            ("Pets", pets.join(", ")),
         ]
     }
 
-    fn to_table(&self) -> Table {
-        Table::new(vec![Row::new(vec![
-            Cell::from(Span::raw(self.id.to_string())),
-            Cell::from(Span::raw(self.name.to_string())),
-            Cell::from(Span::raw(self.age.to_string())),
-            Cell::from(Span::raw(self.birthdate.to_string())),
-        ])])
-        .header(Row::new(vec![
-            Cell::from(Span::styled(
-                "ID",
-                Style::default().add_modifier(Modifier::BOLD),
-            )),
-            Cell::from(Span::styled(
-                "Name",
-                Style::default().add_modifier(Modifier::BOLD),
-            )),
-            Cell::from(Span::styled(
-                "Age",
-                Style::default().add_modifier(Modifier::BOLD),
-            )),
-            Cell::from(Span::styled(
-                "Created At",
-                Style::default().add_modifier(Modifier::BOLD),
-            )),
-        ]))
-        .block(
-            Block::default()
-                .borders(Borders::ALL)
-                .style(Style::default().fg(Color::White))
-                .title("Detail")
-                .border_type(BorderType::Plain),
-        )
-        .widths(&[
-            Constraint::Percentage(10),
-            Constraint::Percentage(25),
-            Constraint::Percentage(10),
-            Constraint::Percentage(25),
-        ])
-    }
-
     fn create_placeholder() -> Self {
         let mut rng = rand::thread_rng();
         Self {
             id: rng.gen_range(0, 9999999),
             name: rng.sample_iter(Alphanumeric).take(10).collect(),
-            age: rng.gen_range(1, 15),
-            birthdate: Utc::now().round_subsecs(0),
+            birthdate: AgeUnit::Years
+                .shift(Utc::now().round_subsecs(0), -rng.gen_range(1, 15))
+                .expect("recent dates can be shifted"),
+            deleted_at: None,
+        }
+    }
+
+    fn fields() -> &'static [Field] {
+        CHILD_FIELDS
+    }
+
+    fn calendar_field() -> &'static str {
+        "birthdate"
+    }
+
+    fn timestamp(&self, field: &str) -> Option<DateTime<Utc>> {
+        match field {
+            "birthdate" => Some(self.birthdate),
+            _ => None,
         }
     }
 }
 
-impl Aged for Child {
-    fn age(&self) -> u8 {
-        self.age
+impl Born for Child {
+    fn birthdate(&self) -> DateTime<Utc> {
+        self.birthdate
     }
 
-    fn set_age<T: Into<u8>>(&mut self, new_age: T) {
-        self.age = new_age.into();
+    fn set_birthdate(&mut self, birthdate: DateTime<Utc>) {
+        self.birthdate = birthdate;
     }
 }
@@ -0,0 +1,490 @@
+use std::collections::HashSet;
+
+use chrono::{prelude::*, Duration};
+
+use rust_commandline_example::audit;
+use rust_commandline_example::backups::{self, BackupKind};
+use rust_commandline_example::child::Child;
+use rust_commandline_example::config::{AgeBounds, Config, CONFIG_PATH};
+use rust_commandline_example::csv_io::{self, ImportMode};
+use rust_commandline_example::data_row::{Aged, DataRow};
+use rust_commandline_example::db_stuff::{Database, Entity, Error, Store, Stored, DB_PATH};
+use rust_commandline_example::migrations::{self, CURRENT_VERSION};
+use rust_commandline_example::pet::Pet;
+use rust_commandline_example::upcoming;
+
+pub(crate) struct Args {
+    pub db_path: String,
+    pub config_path: String,
+    pub as_of: Option<DateTime<Utc>>,
+    pub command: Option<Command>,
+}
+
+/// Subcommands that run without starting the TUI.
+pub(crate) enum Command {
+    Migrate {
+        dry_run: bool,
+    },
+    Import {
+        from: String,
+        kind: Entity,
+        mode: ImportMode,
+    },
+    Export {
+        to: String,
+        kind: Entity,
+    },
+    Convert {
+        to: String,
+    },
+    History {
+        id: usize,
+        kind: Option<Entity>,
+    },
+    BackupsList,
+    BackupsRestore {
+        label: String,
+    },
+    Upcoming {
+        days: Option<u32>,
+        ics: Option<String>,
+    },
+    TrashPurge,
+}
+
+pub(crate) const USAGE: &str = "usage: rust-commandline-example [--db <file>] [command]
+
+  --db <file>       the data file, .json, .yaml or .toml (default ./data/db.json)
+  --config <file>   the settings file (default ./config.toml)
+  --as-of <time>    open the TUI read-only, showing the data as it was at that time
+
+commands:
+  (none)                 start the TUI
+  migrate [--dry-run]    upgrade the data file to the latest version
+  import --from <file.csv> [--kind pets|children] [--append|--replace]
+  export --to <file.csv> [--kind pets|children]
+  convert --to <file>    copy the data into a file of another format
+  history <id> [--kind pets|children]
+                         show every logged change to one record, oldest first
+  backups list           show the backups of the data file, newest first
+  backups restore <timestamp>
+                         replace the data with a backup, the timestamp can be shortened
+  upcoming [--days <n>] [--ics <file.ics>]
+                         list birthdays and adoption anniversaries of the next n days,
+                         optionally also writing them to a calendar file
+  trash purge            delete what has been in the trash for longer than
+                         [trash] purge_after_days";
+
+pub(crate) fn parse_args<I: Iterator<Item = String>>(mut args: I) -> Result<Args, Error> {
+    let mut db_path = DB_PATH.to_owned();
+    let mut config_path = CONFIG_PATH.to_owned();
+    let mut as_of = None;
+    let command = loop {
+        match args.next() {
+            Some(arg) if arg == "--db" => db_path = value_of(&arg, &mut args)?,
+            Some(arg) if arg == "--config" => config_path = value_of(&arg, &mut args)?,
+            Some(arg) if arg == "--as-of" => {
+                let time = value_of(&arg, &mut args)?;
+                as_of = Some(csv_io::parse_datetime(&time).ok_or_else(|| {
+                    Error::Usage(format!("'{}' is not a time I understand", time))
+                })?);
+            }
+            Some(arg) if arg == "--help" || arg == "-h" => {
+                return Err(Error::Usage(USAGE.to_owned()))
+            }
+            other => break other,
+        }
+    };
+    let command = match command {
+        None => None,
+        Some(command) => Some(parse_command(&command, args)?),
+    };
+    if as_of.is_some() && command.is_some() {
+        return Err(Error::Usage(format!(
+            "'--as-of' only works for the TUI\n{}",
+            USAGE
+        )));
+    }
+    Ok(Args {
+        db_path,
+        config_path,
+        as_of,
+        command,
+    })
+}
+
+fn parse_command<I: Iterator<Item = String>>(command: &str, mut rest: I) -> Result<Command, Error> {
+    match command {
+        "migrate" => {
+            let mut dry_run = false;
+            for arg in rest {
+                match arg.as_str() {
+                    "--dry-run" => dry_run = true,
+                    other => return Err(unexpected(other)),
+                }
+            }
+            Ok(Command::Migrate { dry_run })
+        }
+        "import" => {
+            let mut from = None;
+            let mut kind = Entity::Pets;
+            let mut mode = ImportMode::Append;
+            while let Some(arg) = rest.next() {
+                match arg.as_str() {
+                    "--from" => from = Some(value_of(&arg, &mut rest)?),
+                    "--kind" => kind = value_of(&arg, &mut rest)?.parse()?,
+                    "--append" => mode = ImportMode::Append,
+                    "--replace" => mode = ImportMode::Replace,
+                    other => return Err(unexpected(other)),
+                }
+            }
+            let from = from.ok_or_else(|| missing("--from"))?;
+            Ok(Command::Import { from, kind, mode })
+        }
+        "export" => {
+            let mut to = None;
+            let mut kind = Entity::Pets;
+            while let Some(arg) = rest.next() {
+                match arg.as_str() {
+                    "--to" => to = Some(value_of(&arg, &mut rest)?),
+                    "--kind" => kind = value_of(&arg, &mut rest)?.parse()?,
+                    other => return Err(unexpected(other)),
+                }
+            }
+            let to = to.ok_or_else(|| missing("--to"))?;
+            Ok(Command::Export { to, kind })
+        }
+        "convert" => {
+            let mut to = None;
+            while let Some(arg) = rest.next() {
+                match arg.as_str() {
+                    "--to" => to = Some(value_of(&arg, &mut rest)?),
+                    other => return Err(unexpected(other)),
+                }
+            }
+            let to = to.ok_or_else(|| missing("--to"))?;
+            Ok(Command::Convert { to })
+        }
+        "history" => {
+            let mut id = None;
+            let mut kind = None;
+            while let Some(arg) = rest.next() {
+                match arg.as_str() {
+                    "--kind" => kind = Some(value_of(&arg, &mut rest)?.parse()?),
+                    other if id.is_none() => {
+                        id = Some(other.parse().map_err(|_| {
+                            Error::Usage(format!("'{}' is not a record id\n{}", other, USAGE))
+                        })?)
+                    }
+                    other => return Err(unexpected(other)),
+                }
+            }
+            let id = id.ok_or_else(|| missing("<id>"))?;
+            Ok(Command::History { id, kind })
+        }
+        "backups" => match rest.next().as_deref() {
+            Some("list") => match rest.next() {
+                None => Ok(Command::BackupsList),
+                Some(other) => Err(unexpected(&other)),
+            },
+            Some("restore") => {
+                let label = rest.next().ok_or_else(|| missing("<timestamp>"))?;
+                match rest.next() {
+                    None => Ok(Command::BackupsRestore { label }),
+                    Some(other) => Err(unexpected(&other)),
+                }
+            }
+            Some(other) => Err(unexpected(other)),
+            None => Err(missing("list or restore")),
+        },
+        "upcoming" => {
+            let mut days = None;
+            let mut ics = None;
+            while let Some(arg) = rest.next() {
+                match arg.as_str() {
+                    "--days" => {
+                        let value = value_of(&arg, &mut rest)?;
+                        days = Some(value.parse().map_err(|_| {
+                            Error::Usage(format!("'{}' is not a number of days\n{}", value, USAGE))
+                        })?)
+                    }
+                    "--ics" => ics = Some(value_of(&arg, &mut rest)?),
+                    other => return Err(unexpected(other)),
+                }
+            }
+            Ok(Command::Upcoming { days, ics })
+        }
+        "trash" => match rest.next().as_deref() {
+            Some("purge") => match rest.next() {
+                None => Ok(Command::TrashPurge),
+                Some(other) => Err(unexpected(&other)),
+            },
+            Some(other) => Err(unexpected(other)),
+            None => Err(missing("purge")),
+        },
+        other => Err(unexpected(other)),
+    }
+}
+
+fn value_of<I: Iterator<Item = String>>(flag: &str, rest: &mut I) -> Result<String, Error> {
+    rest.next()
+        .ok_or_else(|| Error::Usage(format!("'{}' needs a value\n{}", flag, USAGE)))
+}
+
+fn missing(flag: &str) -> Error {
+    Error::Usage(format!("'{}' is required\n{}", flag, USAGE))
+}
+
+fn unexpected(arg: &str) -> Error {
+    Error::Usage(format!("unexpected argument '{}'\n{}", arg, USAGE))
+}
+
+pub(crate) fn run(command: Command, store: &Store, config: &Config) -> Result<(), Error> {
+    match command {
+        Command::Migrate { dry_run } => migrate(store, dry_run),
+        Command::Import { from, kind, mode } => match kind {
+            Entity::Pets => import_csv::<Pet>(store, &from, mode, &config.ages.pets),
+            Entity::Children => import_csv::<Child>(store, &from, mode, &config.ages.children),
+        },
+        Command::Export { to, kind } => match kind {
+            Entity::Pets => export_csv::<Pet>(store, &to),
+            Entity::Children => export_csv::<Child>(store, &to),
+        },
+        Command::Convert { to } => convert(store, &to),
+        Command::History { id, kind } => show_history(store, id, kind),
+        Command::BackupsList => list_backups(store),
+        Command::BackupsRestore { label } => restore_backup(store, &label),
+        Command::Upcoming { days, ics } => {
+            show_upcoming(store, days.unwrap_or(config.upcoming.days), ics)
+        }
+        Command::TrashPurge => purge_trash(store, config),
+    }
+}
+
+fn migrate(store: &Store, dry_run: bool) -> Result<(), Error> {
+    let raw = store.read_raw()?;
+    let found = migrations::detect_version(&raw)?;
+    if found == CURRENT_VERSION {
+        println!(
+            "{} is already at version {}",
+            store.path.display(),
+            CURRENT_VERSION
+        );
+        return Ok(());
+    }
+    println!(
+        "{} is at version {}, upgrading to version {}",
+        store.path.display(),
+        found,
+        CURRENT_VERSION
+    );
+    let mut notes = Vec::new();
+    let upgraded = migrations::upgrade(raw, &mut notes)?;
+    let db: Database = serde_json::from_value(upgraded)?;
+    for note in notes {
+        println!("  {}", note);
+    }
+    println!(
+        "result: {} pets, {} children",
+        db.pets.len(),
+        db.children.len()
+    );
+    if dry_run {
+        println!("dry run, nothing was written");
+    } else {
+        store.write_after_backup(&db)?;
+        println!("wrote {}", store.path.display());
+    }
+    Ok(())
+}
+
+fn import_csv<T: Stored + Aged>(
+    store: &Store,
+    from: &str,
+    mode: ImportMode,
+    age_bounds: &AgeBounds,
+) -> Result<(), Error> {
+    let mut db = store.read()?;
+    let taken_ids: HashSet<usize> = match mode {
+        ImportMode::Append => T::rows(&db).iter().map(DataRow::id).collect(),
+        ImportMode::Replace => HashSet::new(),
+    };
+    let imported = csv_io::import::<T, _>(from, &db, &taken_ids, age_bounds)?;
+    for column in &imported.ignored_columns {
+        println!("ignoring unknown column '{}'", column);
+    }
+    if !imported.errors.is_empty() {
+        for error in &imported.errors {
+            eprintln!("{}:{}: {}", from, error.line, error.message);
+        }
+        return Err(Error::Import(imported.errors.len()));
+    }
+
+    let count = imported.rows.len();
+    let before = db.clone();
+    match mode {
+        ImportMode::Append => {
+            T::rows_mut(&mut db).extend(imported.rows);
+            store.write(&db)?;
+        }
+        ImportMode::Replace => {
+            *T::rows_mut(&mut db) = imported.rows;
+            store.write_after_backup(&db)?;
+        }
+    }
+    audit::record_changes(store, &before, &db)?;
+    println!(
+        "imported {} {} from {}",
+        count,
+        T::title().to_lowercase(),
+        from
+    );
+    Ok(())
+}
+
+fn export_csv<T: Stored>(store: &Store, to: &str) -> Result<(), Error> {
+    let db = store.read()?;
+    let rows = T::live(&db);
+    csv_io::export(to, &rows)?;
+    println!(
+        "exported {} {} to {}",
+        rows.len(),
+        T::title().to_lowercase(),
+        to
+    );
+    Ok(())
+}
+
+fn convert(store: &Store, to: &str) -> Result<(), Error> {
+    let target = Store::open(to, store.backups.clone())?;
+    if target.path.exists() {
+        return Err(Error::Usage(format!(
+            "'{}' already exists, refusing to overwrite it",
+            to
+        )));
+    }
+    let db = store.read()?;
+    target.write(&db)?;
+    println!(
+        "copied {} pets and {} children from {} to {}",
+        db.pets.len(),
+        db.children.len(),
+        store.path.display(),
+        to
+    );
+    Ok(())
+}
+
+fn list_backups(store: &Store) -> Result<(), Error> {
+    let backups = backups::list(store)?;
+    if backups.is_empty() {
+        println!("no backups of {} yet", store.path.display());
+    }
+    for backup in backups {
+        let kind = match backup.kind {
+            BackupKind::Rolling => "",
+            BackupKind::Daily => "  (daily)",
+        };
+        println!("{}{}", backup.label, kind);
+    }
+    Ok(())
+}
+
+fn restore_backup(store: &Store, label: &str) -> Result<(), Error> {
+    let backup = backups::find(store, label)?;
+    let current = store.read()?;
+    let restored = backups::restore(store, &backup)?;
+    for line in backups::diff(&current, &restored.db) {
+        println!("  {}", line);
+    }
+    println!(
+        "restored {} from {}, the previous data was backed up first",
+        store.path.display(),
+        backup.label
+    );
+    restored.log
+}
+
+fn show_history(store: &Store, id: usize, kind: Option<Entity>) -> Result<(), Error> {
+    let log = audit::history(store, kind, id)?;
+    if log.skipped > 0 {
+        eprintln!(
+            "skipped {} unreadable line(s) of the audit log",
+            log.skipped
+        );
+    }
+    if log.entries.is_empty() {
+        println!("no logged changes for record {}", id);
+    }
+    for entry in log.entries {
+        println!(
+            "{}  {:<10} {:<6}  {}",
+            entry.at.to_rfc3339_opts(SecondsFormat::Secs, true),
+            entry.user,
+            entry.action,
+            entry.describe()
+        );
+    }
+    Ok(())
+}
+
+fn show_upcoming(store: &Store, days: u32, ics: Option<String>) -> Result<(), Error> {
+    let today = Local::now().date_naive();
+    let reminders = upcoming::upcoming(&store.read()?, today, days);
+    if reminders.is_empty() {
+        println!("nothing coming up in the next {} days", days);
+    }
+    for reminder in &reminders {
+        println!(
+            "{}  in {:>3} days  {} {}: {}",
+            reminder.date,
+            (reminder.date - today).num_days(),
+            reminder.entity,
+            reminder.id,
+            reminder
+        );
+    }
+    if let Some(path) = ics {
+        upcoming::write_ics(&path, &reminders)?;
+        println!("wrote {} event(s) to {}", reminders.len(), path);
+    }
+    Ok(())
+}
+
+fn purge_trash(store: &Store, config: &Config) -> Result<(), Error> {
+    let days = config.trash.purge_after_days;
+    if days == 0 {
+        println!("purging is turned off, [trash] purge_after_days is 0");
+        return Ok(());
+    }
+    let (pets, children) = purge_expired_trash(store, config)?;
+    if pets + children == 0 {
+        println!("nothing has been in the trash for more than {} days", days);
+    } else {
+        println!(
+            "purged {} pets and {} children that were in the trash for more than {} days",
+            pets, children, days
+        );
+    }
+    Ok(())
+}
+
+/// Deletes what has been in the trash for longer than `[trash] purge_after_days`, and returns
+/// how many pets and children that was.
+pub(crate) fn purge_expired_trash(store: &Store, config: &Config) -> Result<(usize, usize), Error> {
+    let purge_after_days = config.trash.purge_after_days;
+    if purge_after_days == 0 || !store.path.exists() {
+        return Ok((0, 0));
+    }
+    let before = store.read()?;
+    let mut parsed = before.clone();
+    let cutoff = Utc::now() - Duration::days(purge_after_days.into());
+    let on_delete = config.ownership.on_child_delete;
+    let purged_pets = Pet::purge_trash(&mut parsed, cutoff, on_delete).len();
+    let purged_children = Child::purge_trash(&mut parsed, cutoff, on_delete).len();
+    if purged_pets + purged_children > 0 {
+        store.write_after_backup(&parsed)?;
+        audit::record_changes(store, &before, &parsed)?;
+    }
+    Ok((purged_pets, purged_children))
+}
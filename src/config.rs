@@ -0,0 +1,240 @@
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
+use crate::data_row::AgeUnit;
+use crate::db_stuff::{Entity, Error};
+use crate::keymap::KeyConfig;
+use crate::modal::Navigation;
+use crate::ownership::OnDelete;
+use crate::theme::ThemeConfig;
+
+pub const CONFIG_PATH: &str = "./config.toml";
+
+/// User preferences, read from a TOML file. Every section and key is optional.
+#[derive(Serialize, Deserialize, Clone, Default)]
+#[serde(default)]
+pub struct Config {
+    pub backups: BackupConfig,
+    pub trash: TrashConfig,
+    pub ownership: OwnershipConfig,
+    pub ages: AgeConfig,
+    pub upcoming: UpcomingConfig,
+    /// Keys for the TUI actions, replacing their default keys.
+    pub keys: KeyConfig,
+    pub tui: TuiConfig,
+    pub theme: ThemeConfig,
+    /// The file the config was loaded from, and where settings changed in the TUI are saved.
+    #[serde(skip)]
+    pub path: PathBuf,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct BackupConfig {
+    /// How many of the most recent backups to keep.
+    pub keep: usize,
+    /// How many daily snapshots to keep, one per calendar day (UTC).
+    pub daily: usize,
+}
+
+impl Default for BackupConfig {
+    fn default() -> Self {
+        BackupConfig { keep: 10, daily: 7 }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct TrashConfig {
+    /// Rows that have been in the trash longer than this are dropped for good. 0 keeps them forever.
+    pub purge_after_days: u32,
+}
+
+impl Default for TrashConfig {
+    fn default() -> Self {
+        TrashConfig {
+            purge_after_days: 30,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Default)]
+#[serde(default)]
+pub struct OwnershipConfig {
+    /// What deleting a child that owns pets does: "block", "cascade" or "nullify".
+    pub on_child_delete: OnDelete,
+}
+
+/// The ages edits may set, per kind of row.
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct AgeConfig {
+    pub pets: AgeBounds,
+    pub children: AgeBounds,
+}
+
+impl Default for AgeConfig {
+    fn default() -> Self {
+        AgeConfig {
+            pets: AgeBounds {
+                min: 0,
+                max: 40,
+                unit: AgeUnit::Years,
+            },
+            children: AgeBounds {
+                min: 0,
+                max: 18,
+                unit: AgeUnit::Years,
+            },
+        }
+    }
+}
+
+impl AgeConfig {
+    pub fn of(&self, entity: Entity) -> &AgeBounds {
+        match entity {
+            Entity::Pets => &self.pets,
+            Entity::Children => &self.children,
+        }
+    }
+
+    /// Refuses bounds that no age fits in.
+    fn validate(&self) -> Result<(), Error> {
+        for (name, bounds) in [("pets", &self.pets), ("children", &self.children)] {
+            if bounds.min > bounds.max {
+                return Err(Error::Invalid(format!(
+                    "[ages] {}: min {} is above max {}",
+                    name, bounds.min, bounds.max
+                )));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// An inclusive range of ages, in `unit`s.
+#[derive(Serialize, Deserialize, Clone)]
+pub struct AgeBounds {
+    pub min: u32,
+    pub max: u32,
+    /// What ages are counted in: "years", "months" or "days".
+    #[serde(default)]
+    pub unit: AgeUnit,
+}
+
+impl AgeBounds {
+    pub fn contains(&self, age: u32) -> bool {
+        (self.min..=self.max).contains(&age)
+    }
+
+    /// `age`, moved to the nearest bound if it is outside them.
+    pub fn fit(&self, age: u32) -> u32 {
+        age.clamp(self.min, self.max)
+    }
+
+    /// Whether an age may go from `from` to `to`: into the bounds, or at least towards them, as
+    /// for a child that has grown up.
+    pub fn allows(&self, from: u32, to: u32) -> bool {
+        self.contains(to) || (from < self.min && to > from) || (from > self.max && to < from)
+    }
+
+    /// Why `age` is refused.
+    pub fn refuse<A: fmt::Display>(&self, age: A) -> Error {
+        Error::Invalid(format!(
+            "an age of {} is out of bounds, it has to be {} to {}",
+            age, self.min, self.max
+        ))
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct UpcomingConfig {
+    /// How many days ahead the Upcoming tab and the `upcoming` command look.
+    pub days: u32,
+}
+
+impl Default for UpcomingConfig {
+    fn default() -> Self {
+        UpcomingConfig { days: 30 }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct TuiConfig {
+    /// "keys" to use the keymap as it is, "modal" for vi-style keys on top of it.
+    pub navigation: Navigation,
+    /// Whether clicks and the mouse wheel work in the TUI. Turning it off leaves the mouse to
+    /// the terminal, for selecting text.
+    pub mouse: bool,
+    /// How many columns wide lists next to a detail pane are; by default as wide as their
+    /// longest entry. `<` and `>` change it.
+    pub list_width: Option<u16>,
+    /// How often, in milliseconds, a screen with ages or dates on it is drawn again while nothing
+    /// happens, to keep them current. 0, the default, only draws it when something happens.
+    pub tick_ms: u64,
+}
+
+impl Default for TuiConfig {
+    fn default() -> Self {
+        TuiConfig {
+            navigation: Navigation::default(),
+            mouse: true,
+            list_width: None,
+            tick_ms: 0,
+        }
+    }
+}
+
+impl TuiConfig {
+    /// How often the screen is drawn again while nothing happens, if at all.
+    pub fn tick_rate(&self) -> Option<Duration> {
+        Some(self.tick_ms)
+            .filter(|&ms| ms > 0)
+            .map(Duration::from_millis)
+    }
+}
+
+impl Config {
+    /// A missing file just means "use the defaults".
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, Error> {
+        let mut config = match fs::read_to_string(&path) {
+            Ok(content) => toml::from_str(&content)?,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Config::default(),
+            Err(e) => return Err(e.into()),
+        };
+        config.ages.validate()?;
+        config.path = path.as_ref().to_path_buf();
+        Ok(config)
+    }
+
+    /// Writes `[tui] list_width` to the config file, leaving the rest of it, comments and all,
+    /// as it was. The file is created if there is none yet.
+    pub fn save_list_width(&self) -> Result<(), Error> {
+        let content = match fs::read_to_string(&self.path) {
+            Ok(content) => content,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => String::new(),
+            Err(e) => return Err(e.into()),
+        };
+        let mut doc: toml_edit::DocumentMut = content.parse()?;
+        if !doc.contains_key("tui") {
+            doc["tui"] = toml_edit::table();
+        }
+        match self.tui.list_width {
+            Some(width) => doc["tui"]["list_width"] = toml_edit::value(i64::from(width)),
+            None => {
+                if let Some(tui) = doc["tui"].as_table_like_mut() {
+                    tui.remove("list_width");
+                }
+            }
+        }
+        fs::write(&self.path, doc.to_string())?;
+        Ok(())
+    }
+}
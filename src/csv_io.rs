@@ -0,0 +1,309 @@
+use std::collections::HashSet;
+use std::fs;
+use std::path::Path;
+
+use chrono::prelude::*;
+use rand::prelude::*;
+use serde_json::{Map, Value};
+
+use crate::config::AgeBounds;
+use crate::data_row::{Aged, Field, FieldKind};
+use crate::db_stuff::{Database, Error, Stored};
+
+/// Formats tried, in order, for timestamps without an explicit offset. They are read as UTC.
+const DATETIME_FORMATS: &[&str] = &[
+    "%Y-%m-%d %H:%M:%S",
+    "%Y-%m-%dT%H:%M:%S",
+    "%Y-%m-%d %H:%M",
+    "%d/%m/%Y %H:%M",
+    "%m/%d/%Y %H:%M",
+];
+
+/// Formats tried, in order, for plain dates. They are read as midnight UTC.
+/// Day-first comes before month-first, so `03/04/2020` is the 3rd of April.
+const DATE_FORMATS: &[&str] = &[
+    "%Y-%m-%d", "%d/%m/%Y", "%m/%d/%Y", "%d.%m.%Y", "%d %b %Y", "%b %d %Y", "%B %d %Y", "%d %B %Y",
+];
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum ImportMode {
+    Append,
+    Replace,
+}
+
+pub struct LineError {
+    pub line: u64,
+    pub message: String,
+}
+
+pub struct Imported<T> {
+    pub rows: Vec<T>,
+    pub ignored_columns: Vec<String>,
+    pub errors: Vec<LineError>,
+}
+
+pub fn parse_datetime(input: &str) -> Option<DateTime<Utc>> {
+    let input = input.trim();
+    if let Ok(with_offset) = DateTime::parse_from_rfc3339(input) {
+        return Some(with_offset.with_timezone(&Utc));
+    }
+    if let Ok(with_offset) = DateTime::parse_from_rfc2822(input) {
+        return Some(with_offset.with_timezone(&Utc));
+    }
+    let without_commas = input.replace(',', "");
+    for format in DATETIME_FORMATS {
+        if let Ok(naive) = NaiveDateTime::parse_from_str(&without_commas, format) {
+            return Some(Utc.from_utc_datetime(&naive));
+        }
+    }
+    for format in DATE_FORMATS {
+        if let Some(midnight) = NaiveDate::parse_from_str(&without_commas, format)
+            .ok()
+            .and_then(|date| date.and_hms_opt(0, 0, 0))
+        {
+            return Some(Utc.from_utc_datetime(&midnight));
+        }
+    }
+    None
+}
+
+/// Lower-cases and turns spaces and dashes into underscores, so "Created At" matches `created_at`.
+fn normalize_header(header: &str) -> String {
+    header.trim().to_lowercase().replace([' ', '-'], "_")
+}
+
+fn matches_header(field: &Field, header: &str) -> bool {
+    let header = normalize_header(header);
+    field.name == header || normalize_header(field.label) == header
+}
+
+fn parse_cell(field: &Field, cell: &str) -> Result<Value, String> {
+    let cell = cell.trim();
+    match field.kind {
+        FieldKind::Text => Ok(Value::String(cell.to_owned())),
+        FieldKind::Integer => cell
+            .parse::<u64>()
+            .map(Value::from)
+            .map_err(|_| format!("{}: '{}' is not a whole number", field.name, cell)),
+        FieldKind::Reference if cell.is_empty() => Ok(Value::Null),
+        FieldKind::Reference => cell
+            .parse::<u64>()
+            .map(Value::from)
+            .map_err(|_| format!("{}: '{}' is not an id", field.name, cell)),
+        FieldKind::Timestamp => parse_datetime(cell)
+            .map(|ts| Value::String(ts.to_rfc3339_opts(SecondsFormat::Secs, true)))
+            .ok_or_else(|| format!("{}: '{}' is not a date I understand", field.name, cell)),
+    }
+}
+
+/// Reads every data line of the CSV into a `T`. Rows without an `id` column get a random one,
+/// like `create_placeholder` does, and rows without a `created_at` column were created now.
+/// `taken_ids` are ids the rows must not reuse, and every row must have no `Stored::problem`
+/// with `db` and an age within `age_bounds`. Lines that can't be read or don't pass are
+/// collected in `errors` rather than ending the import.
+pub fn import<T: Stored + Aged, P: AsRef<Path>>(
+    path: P,
+    db: &Database,
+    taken_ids: &HashSet<usize>,
+    age_bounds: &AgeBounds,
+) -> Result<Imported<T>, Error> {
+    let content = fs::read(path)?;
+    let mut lines = LineCounter::new(&content);
+    let mut reader = csv::ReaderBuilder::new()
+        .flexible(true)
+        .from_reader(content.as_slice());
+    let fields = T::fields();
+    let headers = reader.headers()?.clone();
+    let columns: Vec<Option<usize>> = fields
+        .iter()
+        .map(|f| headers.iter().position(|h| matches_header(f, h)))
+        .collect();
+    let ignored_columns = headers
+        .iter()
+        .filter(|h| !fields.iter().any(|f| matches_header(f, h)))
+        .map(|h| h.to_owned())
+        .collect();
+
+    let mut rng = rand::thread_rng();
+    let mut seen_ids = taken_ids.clone();
+    let mut rows = Vec::new();
+    let mut errors = Vec::new();
+    let now = Utc::now().to_rfc3339_opts(SecondsFormat::Secs, true);
+    for record in reader.records() {
+        let record = match record {
+            Ok(record) => record,
+            Err(e) if e.is_io_error() => return Err(e.into()),
+            Err(e) => {
+                errors.push(LineError {
+                    line: e.position().map_or(0, |p| lines.line_of(p)),
+                    message: e.to_string(),
+                });
+                continue;
+            }
+        };
+        let line = record.position().map_or(0, |p| lines.line_of(p));
+        let mut object = Map::new();
+        let mut problems = Vec::new();
+        for (field, column) in fields.iter().zip(&columns) {
+            match column.and_then(|c| record.get(c)) {
+                Some(cell) => match parse_cell(field, cell) {
+                    Ok(value) => {
+                        object.insert(field.name.to_owned(), value);
+                    }
+                    Err(problem) => problems.push(problem),
+                },
+                None if field.name == "id" => {
+                    let id = loop {
+                        let id = rng.gen_range(0, 9999999);
+                        if !seen_ids.contains(&id) {
+                            break id;
+                        }
+                    };
+                    object.insert("id".to_owned(), Value::from(id));
+                }
+                None if field.name == "created_at" => {
+                    object.insert("created_at".to_owned(), Value::String(now.clone()));
+                }
+                None if field.kind == FieldKind::Reference => {}
+                None => problems.push(format!("{}: missing", field.name)),
+            }
+        }
+        if problems.is_empty() {
+            match serde_json::from_value::<T>(Value::Object(object)) {
+                Ok(row) if seen_ids.contains(&row.id()) => {
+                    problems.push(format!("id: {} is already in use", row.id()))
+                }
+                Ok(row) if !age_bounds.contains(row.age(age_bounds.unit)) => {
+                    problems.push(age_bounds.refuse(row.age(age_bounds.unit)).to_string())
+                }
+                Ok(row) => match row.problem(db) {
+                    None => {
+                        seen_ids.insert(row.id());
+                        rows.push(row);
+                    }
+                    Some(problem) => problems.push(format!("the {} {}", T::row_name(), problem)),
+                },
+                Err(e) => problems.push(e.to_string()),
+            }
+        }
+        if !problems.is_empty() {
+            errors.push(LineError {
+                line,
+                message: problems.join("; "),
+            });
+        }
+    }
+    Ok(Imported {
+        rows,
+        ignored_columns,
+        errors,
+    })
+}
+
+/// Works out the lines records start on from their byte offsets. The reader's own line count
+/// is taken where the previous record ended, which is a line early after a `\r\n`.
+struct LineCounter<'a> {
+    content: &'a [u8],
+    counted_to: usize,
+    line: u64,
+}
+
+impl<'a> LineCounter<'a> {
+    fn new(content: &'a [u8]) -> Self {
+        LineCounter {
+            content,
+            counted_to: 0,
+            line: 1,
+        }
+    }
+
+    /// The line of the record at `position`. Positions have to come in order.
+    fn line_of(&mut self, position: &csv::Position) -> u64 {
+        let from = (position.byte() as usize).min(self.content.len());
+        let start = from
+            + self.content[from..]
+                .iter()
+                .take_while(|&&b| b == b'\r' || b == b'\n')
+                .count();
+        if start > self.counted_to {
+            let breaks = self.content[self.counted_to..start]
+                .iter()
+                .filter(|&&b| b == b'\n')
+                .count();
+            self.line += breaks as u64;
+            self.counted_to = start;
+        }
+        self.line
+    }
+}
+
+/// Writes one column per field of `T`, in the order of `T::fields()`, with timestamps as RFC 3339.
+pub fn export<T: Stored, P: AsRef<Path>>(path: P, rows: &[T]) -> Result<(), Error> {
+    let mut writer = csv::Writer::from_path(path)?;
+    let fields = T::fields();
+    writer.write_record(fields.iter().map(|f| f.name))?;
+    for row in rows {
+        let value = serde_json::to_value(row)?;
+        writer.write_record(fields.iter().map(|f| match &value[f.name] {
+            Value::Null => String::new(),
+            Value::String(s) => s.clone(),
+            other => other.to_string(),
+        }))?;
+    }
+    writer.flush()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn utc(text: &str) -> Option<DateTime<Utc>> {
+        Some(text.parse().unwrap())
+    }
+
+    /// The line each record of `content` starts on, as `import` reports them.
+    fn record_lines(content: &str) -> Vec<u64> {
+        let mut lines = LineCounter::new(content.as_bytes());
+        let mut reader = csv::Reader::from_reader(content.as_bytes());
+        reader
+            .records()
+            .map(|record| lines.line_of(record.unwrap().position().unwrap()))
+            .collect()
+    }
+
+    #[test]
+    fn reads_offsets_and_plain_timestamps() {
+        let expected = utc("2020-04-03T10:30:00Z");
+        assert_eq!(parse_datetime("2020-04-03T12:30:00+02:00"), expected);
+        assert_eq!(parse_datetime("Fri, 03 Apr 2020 10:30:00 +0000"), expected);
+        assert_eq!(parse_datetime(" 2020-04-03 10:30:00 "), expected);
+        assert_eq!(parse_datetime("2020-04-03 10:30"), expected);
+        assert_eq!(parse_datetime("2020-04-03"), utc("2020-04-03T00:00:00Z"));
+        assert_eq!(parse_datetime("April 3, 2020"), utc("2020-04-03T00:00:00Z"));
+        assert_eq!(parse_datetime("yesterday"), None);
+    }
+
+    #[test]
+    fn reads_slashed_dates_day_first_unless_that_cannot_be() {
+        assert_eq!(parse_datetime("03/04/2020"), utc("2020-04-03T00:00:00Z"));
+        assert_eq!(parse_datetime("04/13/2020"), utc("2020-04-13T00:00:00Z"));
+        assert_eq!(
+            parse_datetime("03/04/2020 10:30"),
+            utc("2020-04-03T10:30:00Z")
+        );
+        assert_eq!(
+            parse_datetime("04/13/2020 10:30"),
+            utc("2020-04-13T10:30:00Z")
+        );
+        assert_eq!(parse_datetime("13/13/2020"), None);
+    }
+
+    #[test]
+    fn counts_lines_across_crlf_blank_lines_and_quoted_breaks() {
+        assert_eq!(record_lines("name\na\nb\n"), [2, 3]);
+        assert_eq!(record_lines("name\r\na\r\nb\r\n"), [2, 3]);
+        assert_eq!(record_lines("name\n\na\n\r\n\nb\n"), [3, 6]);
+        assert_eq!(record_lines("name\n\"a\nstill a\"\nb\n"), [2, 4]);
+    }
+}
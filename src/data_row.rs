@@ -1,32 +1,449 @@
-use tui::{text::Spans, widgets::Table};
+use std::convert::TryFrom;
+use std::fmt;
+
+use chrono::{prelude::*, Duration, Months};
+use serde::{Deserialize, Serialize};
+
+use crate::config::AgeBounds;
+use crate::db_stuff::{Database, Error};
+
+/// How a stored field is represented, so generic code (like CSV import) knows how to parse it.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum FieldKind {
+    Integer,
+    Text,
+    Timestamp,
+    /// The id of a row of another kind, or nothing.
+    Reference,
+}
+
+/// A serialized field of a row: `name` is the serde key, `label` is what a human sees.
+pub struct Field {
+    pub name: &'static str,
+    pub label: &'static str,
+    pub kind: FieldKind,
+}
 
 pub trait DataRow {
+    fn id(&self) -> usize;
     fn name(&self) -> String;
     fn set_name<T: Into<String>>(&mut self, new_name: T);
 
-    fn to_table(&self) -> Table;
+    /// What the detail pane shows of the row, as labels and values; `db` is there to look up
+    /// the rows it is related to, and ages are shown in `unit`s.
+    fn detail(&self, db: &Database, unit: AgeUnit) -> Vec<(&'static str, String)>;
     fn cli_name() -> String;
-    fn help_part<'a>() -> Vec<Spans<'a>>;
-    fn menu_titles() -> Vec<&'static str>;
     fn title() -> &'static str;
     fn row_name() -> &'static str;
     fn create_placeholder() -> Self;
+    fn fields() -> &'static [Field];
+    /// The `Timestamp` field the calendar starts out plotting the rows by.
+    fn calendar_field() -> &'static str;
+    /// The value of the `Timestamp` field `field`.
+    fn timestamp(&self, field: &str) -> Option<DateTime<Utc>>;
+
+    /// What the Stats screen counts the rows by, if they come in groups.
+    fn group(&self) -> Option<String> {
+        None
+    }
+}
+
+/// How timestamps are shown: in local time, though they are stored in UTC.
+pub fn local_time(at: &DateTime<Utc>) -> String {
+    at.with_timezone(&Local)
+        .format("%Y-%m-%d %H:%M %:z")
+        .to_string()
+}
+
+/// An order for the rows of a list: by one of their fields, or by age, youngest first.
+#[derive(Copy, Clone)]
+pub struct SortKey {
+    name: &'static str,
+    field: &'static Field,
+    descending: bool,
+}
+
+/// A field value as the rows are sorted by it; rows without one come last.
+#[derive(PartialEq, Eq, PartialOrd, Ord)]
+enum SortValue {
+    Number(u64),
+    Text(String),
+    Time(DateTime<Utc>),
+    Missing,
+}
+
+impl SortKey {
+    /// `age`, or the name or label of one of `T`'s fields, in any case.
+    pub fn parse<T: DataRow>(name: &str) -> Result<Self, Error> {
+        let name = name.trim();
+        let (field_name, descending) = match name.to_lowercase().as_str() {
+            "age" => ("birthdate", true),
+            _ => (name, false),
+        };
+        T::fields()
+            .iter()
+            .find(|f| {
+                f.name.eq_ignore_ascii_case(field_name) || f.label.eq_ignore_ascii_case(field_name)
+            })
+            .map(|field| SortKey {
+                name: if descending { "age" } else { field.name },
+                field,
+                descending,
+            })
+            .ok_or_else(|| {
+                Error::Invalid(format!(
+                    "can't sort by '{}', try {}",
+                    name,
+                    Self::options::<T>().join(", ")
+                ))
+            })
+    }
+
+    /// What `T`'s rows can be sorted by.
+    pub fn options<T: DataRow>() -> Vec<&'static str> {
+        let mut options: Vec<&'static str> = T::fields().iter().map(|f| f.name).collect();
+        if options.contains(&"birthdate") {
+            options.insert(0, "age");
+        }
+        options
+    }
+
+    pub fn reversed(self) -> Self {
+        SortKey {
+            descending: !self.descending,
+            ..self
+        }
+    }
+
+    pub fn sort<T: Serialize>(&self, rows: &mut Vec<T>) {
+        let mut keyed: Vec<(SortValue, T)> = rows
+            .drain(..)
+            .map(|row| (self.value_of(&row), row))
+            .collect();
+        keyed.sort_by(|(a, _), (b, _)| match (a, b) {
+            (SortValue::Missing, _) | (_, SortValue::Missing) => a.cmp(b),
+            _ if self.descending => b.cmp(a),
+            _ => a.cmp(b),
+        });
+        rows.extend(keyed.into_iter().map(|(_, row)| row));
+    }
+
+    fn value_of<T: Serialize>(&self, row: &T) -> SortValue {
+        let value = match serde_json::to_value(row) {
+            Ok(value) => value[self.field.name].clone(),
+            Err(_) => return SortValue::Missing,
+        };
+        match self.field.kind {
+            FieldKind::Integer | FieldKind::Reference => value.as_u64().map(SortValue::Number),
+            FieldKind::Text => value.as_str().map(|s| SortValue::Text(s.to_lowercase())),
+            FieldKind::Timestamp => serde_json::from_value(value).ok().map(SortValue::Time),
+        }
+        .unwrap_or(SortValue::Missing)
+    }
+}
+
+impl fmt::Display for SortKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let natural = self.name == "age";
+        if self.descending == natural {
+            f.write_str(self.name)
+        } else {
+            write!(f, "{}, reversed", self.name)
+        }
+    }
+}
+
+/// What the age of a `Born` row is counted in.
+#[derive(Serialize, Deserialize, Copy, Clone, Debug, PartialEq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum AgeUnit {
+    #[default]
+    Years,
+    Months,
+    Days,
+}
+
+impl AgeUnit {
+    /// How many whole units lie between `from` and `to`; nothing if `to` comes first.
+    pub fn between(self, from: DateTime<Utc>, to: DateTime<Utc>) -> u32 {
+        if to <= from {
+            return 0;
+        }
+        let months = || {
+            let months = (to.year() - from.year()) * 12 + to.month() as i32 - from.month() as i32;
+            let short = (to.day(), to.time()) < (from.day(), from.time());
+            u32::try_from(months - short as i32).unwrap_or(u32::MAX)
+        };
+        match self {
+            AgeUnit::Years => months() / 12,
+            AgeUnit::Months => months(),
+            AgeUnit::Days => u32::try_from((to - from).num_days()).unwrap_or(u32::MAX),
+        }
+    }
+
+    /// `at` moved `by` units, into the future when `by` is positive.
+    pub fn shift(self, at: DateTime<Utc>, by: i64) -> Option<DateTime<Utc>> {
+        let months = match self {
+            AgeUnit::Days => return at.checked_add_signed(Duration::try_days(by)?),
+            AgeUnit::Months => by,
+            AgeUnit::Years => by.checked_mul(12)?,
+        };
+        let abs = Months::new(u32::try_from(months.unsigned_abs()).ok()?);
+        if months >= 0 {
+            at.checked_add_months(abs)
+        } else {
+            at.checked_sub_months(abs)
+        }
+    }
 }
 
+impl fmt::Display for AgeUnit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.pad(match self {
+            AgeUnit::Years => "years",
+            AgeUnit::Months => "months",
+            AgeUnit::Days => "days",
+        })
+    }
+}
+
+/// Rows with an age, counted in whichever `AgeUnit` the caller asks for.
 pub trait Aged {
-    fn age(&self) -> u8;
-    fn set_age<T: Into<u8>>(&mut self, new_age: T);
-    fn decr_age<T: Into<u8>>(&mut self, decr_amt: T) {
-        let real_decr_amt = decr_amt.into();
-        if self.age() <= real_decr_amt {
-            self.set_age(0);
+    fn age(&self, unit: AgeUnit) -> u32;
+    fn set_age<T: Into<u32>>(&mut self, new_age: T, unit: AgeUnit);
+
+    /// The age moved by `shift`, or `None` if that would go below 0 or past `u32::MAX`.
+    fn checked_shifted_age(&self, shift: i8, unit: AgeUnit) -> Option<u32> {
+        let magnitude = u32::from(shift.unsigned_abs());
+        if shift < 0 {
+            self.age(unit).checked_sub(magnitude)
+        } else {
+            self.age(unit).checked_add(magnitude)
+        }
+    }
+
+    /// The age moved by `shift`, stopping at 0 and `u32::MAX`.
+    fn saturating_shifted_age(&self, shift: i8, unit: AgeUnit) -> u32 {
+        let magnitude = u32::from(shift.unsigned_abs());
+        if shift < 0 {
+            self.age(unit).saturating_sub(magnitude)
         } else {
-            let new_age = self.age() - real_decr_amt;
-            self.set_age(new_age);
+            self.age(unit).saturating_add(magnitude)
         }
     }
-    fn incr_age<T: Into<u8>>(&mut self, incr_amt: T) {
-        let new_age = self.age() + incr_amt.into();
-        self.set_age(new_age);
+
+    /// Moves the age by `shift`, as long as it ends up within `bounds`. An age that is already
+    /// out of bounds, like a child that has grown up, may still be moved back towards them.
+    fn shift_age(&mut self, shift: i8, bounds: &AgeBounds) -> Result<u32, Error> {
+        let age = self.age(bounds.unit);
+        match self.checked_shifted_age(shift, bounds.unit) {
+            Some(new_age) if bounds.allows(age, new_age) => {
+                self.set_age(new_age, bounds.unit);
+                Ok(new_age)
+            }
+            _ => Err(bounds.refuse(i64::from(age) + i64::from(shift))),
+        }
+    }
+
+    /// Brings the age into `bounds` if it is outside them, as for a freshly made row.
+    fn fit_age(&mut self, bounds: &AgeBounds) {
+        let age = self.age(bounds.unit);
+        if !bounds.contains(age) {
+            self.set_age(bounds.fit(age), bounds.unit);
+        }
+    }
+}
+
+/// Rows that know when they were born. Their age isn't stored but worked out from the birth
+/// date, so it keeps up with time; changing the age moves the birth date instead.
+pub trait Born {
+    fn birthdate(&self) -> DateTime<Utc>;
+    fn set_birthdate(&mut self, birthdate: DateTime<Utc>);
+
+    /// The age in the largest unit it fills at least once, like "3 years" or "5 months", or
+    /// else in `unit` when ages aren't counted in years.
+    fn age_text(&self, unit: AgeUnit) -> String {
+        let now = Utc::now();
+        if unit != AgeUnit::Years {
+            return format!("{} {}", unit.between(self.birthdate(), now), unit);
+        }
+        [AgeUnit::Years, AgeUnit::Months, AgeUnit::Days]
+            .iter()
+            .map(|&unit| (unit.between(self.birthdate(), now), unit))
+            .find(|&(age, _)| age > 0)
+            .map_or_else(
+                || "newborn".to_owned(),
+                |(age, unit)| format!("{} {}", age, unit),
+            )
+    }
+}
+
+impl<R: Born> Aged for R {
+    fn age(&self, unit: AgeUnit) -> u32 {
+        unit.between(self.birthdate(), Utc::now())
+    }
+
+    fn set_age<T: Into<u32>>(&mut self, new_age: T, unit: AgeUnit) {
+        let younger_by = i64::from(self.age(unit)) - i64::from(new_age.into());
+        if let Some(birthdate) = unit.shift(self.birthdate(), younger_by) {
+            self.set_birthdate(birthdate);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pet::Pet;
+
+    fn pet(id: usize, name: &str, birthdate: &str, owner: Option<usize>) -> Pet {
+        serde_json::from_value(serde_json::json!({
+            "id": id,
+            "name": name,
+            "category": "cats",
+            "birthdate": birthdate,
+            "created_at": "2020-01-01T00:00:00Z",
+            "owner": owner,
+        }))
+        .unwrap()
+    }
+
+    fn pets() -> Vec<Pet> {
+        vec![
+            pet(1, "tacos", "2015-03-16T12:00:00Z", Some(7)),
+            pet(2, "Nacho", "2018-08-20T12:00:00Z", None),
+            pet(3, "mischka", "2016-11-23T12:00:00Z", Some(5)),
+        ]
+    }
+
+    fn ids_sorted_by(key: SortKey) -> Vec<usize> {
+        let mut rows = pets();
+        key.sort(&mut rows);
+        rows.iter().map(|row| row.id).collect()
+    }
+
+    #[test]
+    fn sorts_text_ignoring_case() {
+        let by_name = SortKey::parse::<Pet>("name").unwrap();
+        assert_eq!(ids_sorted_by(by_name), [3, 2, 1]);
+        assert_eq!(ids_sorted_by(by_name.reversed()), [1, 2, 3]);
+    }
+
+    #[test]
+    fn sorts_by_age_youngest_first() {
+        let by_age = SortKey::parse::<Pet>("Age").unwrap();
+        assert_eq!(ids_sorted_by(by_age), [2, 3, 1]);
+        assert_eq!(ids_sorted_by(by_age.reversed()), [1, 3, 2]);
+        assert_eq!(by_age.to_string(), "age");
+        assert_eq!(by_age.reversed().to_string(), "age, reversed");
+    }
+
+    #[test]
+    fn puts_missing_values_last_either_way() {
+        let by_owner = SortKey::parse::<Pet>("Owner").unwrap();
+        assert_eq!(ids_sorted_by(by_owner), [3, 1, 2]);
+        assert_eq!(ids_sorted_by(by_owner.reversed()), [1, 3, 2]);
+    }
+
+    #[test]
+    fn refuses_unknown_fields() {
+        assert!(SortKey::parse::<Pet>("colour").is_err());
+    }
+
+    fn utc(text: &str) -> DateTime<Utc> {
+        text.parse().unwrap()
+    }
+
+    #[test]
+    fn counts_only_whole_units() {
+        let born = utc("2020-01-31T12:00:00Z");
+        assert_eq!(AgeUnit::Years.between(born, utc("2021-01-31T11:59:59Z")), 0);
+        assert_eq!(AgeUnit::Years.between(born, utc("2021-01-31T12:00:00Z")), 1);
+        assert_eq!(
+            AgeUnit::Months.between(born, utc("2020-02-29T12:00:00Z")),
+            0
+        );
+        assert_eq!(
+            AgeUnit::Months.between(born, utc("2020-03-31T12:00:00Z")),
+            2
+        );
+        assert_eq!(AgeUnit::Days.between(born, utc("2020-03-01T11:00:00Z")), 29);
+        assert_eq!(AgeUnit::Days.between(born, utc("2019-01-01T00:00:00Z")), 0);
+    }
+
+    #[test]
+    fn shifts_by_calendar_units_clamping_to_the_end_of_the_month() {
+        let born = utc("2020-01-31T12:00:00Z");
+        assert_eq!(
+            AgeUnit::Months.shift(born, 1),
+            Some(utc("2020-02-29T12:00:00Z"))
+        );
+        assert_eq!(
+            AgeUnit::Years.shift(born, -2),
+            Some(utc("2018-01-31T12:00:00Z"))
+        );
+        assert_eq!(
+            AgeUnit::Days.shift(born, -31),
+            Some(utc("2019-12-31T12:00:00Z"))
+        );
+        assert_eq!(AgeUnit::Years.shift(born, i64::MAX), None);
+        assert_eq!(AgeUnit::Days.shift(born, i64::MIN), None);
+    }
+
+    #[test]
+    fn sets_ages_by_moving_the_birthdate() {
+        let mut tacos = pet(1, "tacos", "2015-03-16T12:00:00Z", None);
+        for &unit in &[AgeUnit::Years, AgeUnit::Months, AgeUnit::Days] {
+            tacos.set_age(3u32, unit);
+            assert_eq!(tacos.age(unit), 3, "{}", unit);
+        }
+        tacos.set_age(0u32, AgeUnit::Years);
+        assert_eq!(tacos.age_text(AgeUnit::Years), "3 days");
+    }
+
+    fn bounds(min: u32, max: u32, unit: AgeUnit) -> AgeBounds {
+        AgeBounds { min, max, unit }
+    }
+
+    #[test]
+    fn shifts_ages_within_bounds_or_back_towards_them() {
+        let mut tacos = pet(1, "tacos", "2015-03-16T12:00:00Z", None);
+        tacos.set_age(5u32, AgeUnit::Years);
+        let years = bounds(1, 5, AgeUnit::Years);
+        assert!(tacos.shift_age(1, &years).is_err());
+        assert_eq!(tacos.shift_age(-1, &years).unwrap(), 4);
+
+        let grown_up = bounds(0, 2, AgeUnit::Years);
+        assert!(tacos.shift_age(1, &grown_up).is_err());
+        assert_eq!(tacos.shift_age(-1, &grown_up).unwrap(), 3);
+
+        tacos.set_age(36u32, AgeUnit::Months);
+        let months = bounds(0, 240, AgeUnit::Months);
+        assert_eq!(tacos.shift_age(1, &months).unwrap(), 37);
+        assert_eq!(tacos.age(AgeUnit::Years), 3);
+    }
+
+    #[test]
+    fn stops_shifted_ages_at_zero() {
+        let mut newborn = pet(1, "nacho", "2015-03-16T12:00:00Z", None);
+        newborn.set_age(0u32, AgeUnit::Days);
+        assert_eq!(newborn.checked_shifted_age(-1, AgeUnit::Days), None);
+        assert_eq!(newborn.saturating_shifted_age(-1, AgeUnit::Days), 0);
+        assert_eq!(newborn.checked_shifted_age(1, AgeUnit::Days), Some(1));
+        assert!(newborn
+            .shift_age(-1, &bounds(0, 10, AgeUnit::Days))
+            .is_err());
+    }
+
+    #[test]
+    fn fits_ages_to_the_nearest_bound() {
+        let mut tacos = pet(1, "tacos", "2015-03-16T12:00:00Z", None);
+        tacos.set_age(20u32, AgeUnit::Years);
+        tacos.fit_age(&bounds(1, 15, AgeUnit::Years));
+        assert_eq!(tacos.age(AgeUnit::Years), 15);
+        tacos.fit_age(&bounds(200, 300, AgeUnit::Months));
+        assert_eq!(tacos.age(AgeUnit::Months), 200);
+        tacos.fit_age(&bounds(0, 10000, AgeUnit::Days));
+        assert_eq!(tacos.age(AgeUnit::Months), 200);
     }
 }
@@ -0,0 +1,290 @@
+use chrono::{prelude::*, Duration, LocalResult, Months};
+use crossterm::event::KeyCode;
+use tui::{
+    text::{Span, Spans},
+    widgets::{Block, BorderType, Borders, Paragraph},
+};
+
+use crate::theme::{Role, Theme};
+
+/// The part of the date the arrow keys change, or typing it out in full.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum DatePart {
+    Day,
+    Month,
+    Year,
+    Text,
+}
+
+/// Editing a timestamp in local time; what comes out is UTC again.
+pub struct DatePicker {
+    pub title: String,
+    pub value: DateTime<Local>,
+    pub part: DatePart,
+    pub text: String,
+    pub error: Option<String>,
+}
+
+/// What a key press did to the picker.
+pub enum Picked {
+    Editing,
+    Done(DateTime<Utc>),
+    Cancelled,
+}
+
+impl DatePicker {
+    pub fn new(title: &str, initial: DateTime<Utc>) -> Self {
+        DatePicker {
+            title: title.to_owned(),
+            value: initial.with_timezone(&Local),
+            part: DatePart::Day,
+            text: String::new(),
+            error: None,
+        }
+    }
+
+    pub fn handle(&mut self, key: KeyCode) -> Picked {
+        match (self.part, key) {
+            (_, KeyCode::Esc) => return Picked::Cancelled,
+            (DatePart::Text, KeyCode::Enter) => match parse_iso8601(&self.text, self.value) {
+                Ok(value) => {
+                    self.value = value;
+                    return self.confirm();
+                }
+                Err(e) => self.error = Some(e),
+            },
+            (_, KeyCode::Enter) => return self.confirm(),
+            (DatePart::Text, KeyCode::Tab) => self.part = DatePart::Day,
+            (_, KeyCode::Tab) => {
+                self.text = self.value.format("%Y-%m-%dT%H:%M").to_string();
+                self.part = DatePart::Text;
+            }
+            (DatePart::Text, KeyCode::Backspace) => {
+                let _ = self.text.pop();
+            }
+            (DatePart::Text, KeyCode::Char(c)) => self.text.push(c),
+            (DatePart::Text, _) => {}
+            (part, KeyCode::Left) => {
+                self.part = match part {
+                    DatePart::Year => DatePart::Month,
+                    _ => DatePart::Day,
+                }
+            }
+            (part, KeyCode::Right) => {
+                self.part = match part {
+                    DatePart::Day => DatePart::Month,
+                    _ => DatePart::Year,
+                }
+            }
+            (part, KeyCode::Up) => self.spin(part, 1),
+            (part, KeyCode::Down) => self.spin(part, -1),
+            _ => {}
+        }
+        Picked::Editing
+    }
+
+    fn confirm(&mut self) -> Picked {
+        if self.value > Local::now() {
+            self.error = Some(format!(
+                "{} is in the future",
+                self.value.format("%Y-%m-%d %H:%M")
+            ));
+            return Picked::Editing;
+        }
+        Picked::Done(self.value.with_timezone(&Utc))
+    }
+
+    /// Moves the focused part by `by`. Days roll over into the next or previous month, months
+    /// and years keep the day where it can, like January 31 plus a month becoming February 28.
+    fn spin(&mut self, part: DatePart, by: i32) {
+        let naive = self.value.naive_local();
+        let months = Months::new(by.unsigned_abs() * if part == DatePart::Year { 12 } else { 1 });
+        let moved = match part {
+            DatePart::Day => naive.checked_add_signed(Duration::days(by.into())),
+            _ if by < 0 => naive.checked_sub_months(months),
+            _ => naive.checked_add_months(months),
+        };
+        match moved.map(|naive| in_local_time(&naive)) {
+            Some(Ok(value)) => {
+                self.value = value;
+                self.error = None;
+            }
+            Some(Err(e)) => self.error = Some(e),
+            None => {}
+        }
+    }
+
+    pub fn render(&self, theme: &Theme) -> Paragraph<'_> {
+        let focused = |part: DatePart, text: String| {
+            let style = if self.part == part {
+                theme.highlight()
+            } else {
+                theme.style(Role::Text)
+            };
+            Span::styled(text, style)
+        };
+        let mut lines = vec![
+            Spans::from(vec![
+                Span::raw("  "),
+                focused(DatePart::Day, self.value.format("%d").to_string()),
+                Span::raw(" "),
+                focused(DatePart::Month, self.value.format("%b").to_string()),
+                Span::raw(" "),
+                focused(DatePart::Year, self.value.format("%Y").to_string()),
+                Span::raw(self.value.format("  %H:%M local time (UTC%:z)").to_string()),
+            ]),
+            Spans::from(vec![
+                Span::raw("  or type it: "),
+                match self.part {
+                    DatePart::Text => focused(DatePart::Text, self.text.clone() + "_"),
+                    _ => Span::styled("Tab", theme.style(Role::Muted)),
+                },
+            ]),
+        ];
+        if let Some(error) = &self.error {
+            lines.push(Spans::from(vec![Span::styled(
+                format!("  {}", error),
+                theme.style(Role::Error),
+            )]));
+        }
+        Paragraph::new(lines).block(
+            Block::default()
+                .borders(Borders::ALL)
+                .style(theme.style(Role::Accent))
+                .title(format!(
+                    "{} (Left/Right pick, Up/Down change, Enter saves, Esc cancels)",
+                    self.title
+                ))
+                .border_type(BorderType::Plain),
+        )
+    }
+}
+
+fn in_local_time(naive: &NaiveDateTime) -> Result<DateTime<Local>, String> {
+    match Local.from_local_datetime(naive) {
+        LocalResult::Single(at) => Ok(at),
+        // When the clocks go back an hour, take the first of the two.
+        LocalResult::Ambiguous(earliest, _) => Ok(earliest),
+        LocalResult::None => Err(format!(
+            "{} doesn't exist in local time",
+            naive.format("%Y-%m-%d %H:%M")
+        )),
+    }
+}
+
+/// Reads an ISO 8601 date and time. Without an offset it's local time; a date alone keeps the
+/// time of day of `current`.
+fn parse_iso8601(text: &str, current: DateTime<Local>) -> Result<DateTime<Local>, String> {
+    let text = text.trim();
+    if let Ok(at) = DateTime::parse_from_rfc3339(text) {
+        return Ok(at.with_timezone(&Local));
+    }
+    for format in [
+        "%Y-%m-%dT%H:%M:%S",
+        "%Y-%m-%dT%H:%M",
+        "%Y-%m-%d %H:%M:%S",
+        "%Y-%m-%d %H:%M",
+    ] {
+        if let Ok(naive) = NaiveDateTime::parse_from_str(text, format) {
+            return in_local_time(&naive);
+        }
+    }
+    match NaiveDate::parse_from_str(text, "%Y-%m-%d") {
+        Ok(date) => in_local_time(&date.and_time(current.time())),
+        Err(_) => Err(format!(
+            "'{}' isn't an ISO 8601 date, like 2020-08-20 or 2020-08-20T12:00",
+            text
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A picker at noon local time, so moving it never runs into a clock change.
+    fn picker(day: &str) -> DatePicker {
+        let noon = NaiveDate::parse_from_str(day, "%Y-%m-%d")
+            .unwrap()
+            .and_hms_opt(12, 0, 0)
+            .unwrap();
+        DatePicker::new(
+            "Birthdate",
+            in_local_time(&noon).unwrap().with_timezone(&Utc),
+        )
+    }
+
+    fn shown(picker: &DatePicker) -> String {
+        picker.value.format("%Y-%m-%d %H:%M").to_string()
+    }
+
+    fn press(picker: &mut DatePicker, keys: &[KeyCode]) {
+        for &key in keys {
+            assert!(matches!(picker.handle(key), Picked::Editing));
+        }
+    }
+
+    #[test]
+    fn rolls_days_over_and_keeps_the_day_for_months_and_years() {
+        let mut picker = picker("2020-01-31");
+        press(&mut picker, &[KeyCode::Up]);
+        assert_eq!(shown(&picker), "2020-02-01 12:00");
+        press(&mut picker, &[KeyCode::Down, KeyCode::Right, KeyCode::Up]);
+        assert_eq!(shown(&picker), "2020-02-29 12:00");
+        press(
+            &mut picker,
+            &[KeyCode::Right, KeyCode::Right, KeyCode::Down],
+        );
+        assert_eq!(picker.part, DatePart::Year);
+        assert_eq!(shown(&picker), "2019-02-28 12:00");
+        press(&mut picker, &[KeyCode::Left, KeyCode::Left, KeyCode::Left]);
+        assert_eq!(picker.part, DatePart::Day);
+    }
+
+    #[test]
+    fn takes_typed_dates_keeping_the_time_without_one() {
+        let mut picker = picker("2020-01-31");
+        press(&mut picker, &[KeyCode::Tab]);
+        assert_eq!(picker.text, "2020-01-31T12:00");
+        picker.text.clear();
+        press(&mut picker, &[KeyCode::Char('2'), KeyCode::Char('x')]);
+        press(&mut picker, &[KeyCode::Backspace, KeyCode::Enter]);
+        assert!(picker.error.is_some());
+        picker.text = "2018-08-20".to_owned();
+        match picker.handle(KeyCode::Enter) {
+            Picked::Done(at) => {
+                assert_eq!(shown(&DatePicker::new("", at)), "2018-08-20 12:00")
+            }
+            _ => panic!("the typed date wasn't taken"),
+        }
+    }
+
+    #[test]
+    fn refuses_dates_in_the_future() {
+        let mut picker = picker("2020-01-31");
+        press(&mut picker, &[KeyCode::Right, KeyCode::Right]);
+        for _ in 0..200 {
+            press(&mut picker, &[KeyCode::Up]);
+        }
+        press(&mut picker, &[KeyCode::Enter]);
+        assert!(picker
+            .error
+            .as_deref()
+            .unwrap()
+            .ends_with("is in the future"));
+        assert!(matches!(picker.handle(KeyCode::Esc), Picked::Cancelled));
+    }
+
+    #[test]
+    fn reads_iso_8601_with_and_without_an_offset() {
+        let current = picker("2020-01-31").value;
+        let utc = |text| parse_iso8601(text, current).unwrap().with_timezone(&Utc);
+        assert_eq!(
+            utc("2020-08-20T12:00:00+02:00").to_rfc3339(),
+            "2020-08-20T10:00:00+00:00"
+        );
+        let local = parse_iso8601("2020-08-20 08:15", current).unwrap();
+        assert_eq!(local.naive_local().to_string(), "2020-08-20 08:15:00");
+        assert!(parse_iso8601("20 August", current).is_err());
+    }
+}
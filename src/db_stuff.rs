@@ -1,2 +1,343 @@
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use chrono::prelude::*;
+use rand::seq::SliceRandom;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
 use thiserror::Error;
 
+use crate::backups;
+use crate::category::Category;
+use crate::child::Child;
+use crate::config::BackupConfig;
+use crate::data_row::DataRow;
+use crate::migrations;
+use crate::ownership::OnDelete;
+use crate::pet::Pet;
+
+pub const DB_PATH: &str = "./data/db.json";
+
+#[allow(clippy::enum_variant_names)]
+#[derive(Error, Debug)]
+pub enum Error {
+    #[error("error reading the DB file: {0}")]
+    ReadDBError(#[from] io::Error),
+    #[error("error parsing the DB file: {0}")]
+    ParseDBError(#[from] serde_json::Error),
+    #[error("error reading or writing YAML: {0}")]
+    Yaml(#[from] serde_yaml::Error),
+    #[error("error parsing TOML: {0}")]
+    TomlParse(#[from] toml::de::Error),
+    #[error("error writing TOML: {0}")]
+    TomlWrite(#[from] toml::ser::Error),
+    #[error("error parsing TOML: {0}")]
+    TomlEdit(#[from] toml_edit::TomlError),
+    #[error("don't know how to store data in '{0}', use a .json, .yaml or .toml file")]
+    UnknownFormat(String),
+    #[error("the DB file is at version {found}, this build only understands up to {supported}")]
+    UnsupportedVersion { found: u64, supported: u64 },
+    #[error("error migrating the DB file: {0}")]
+    Migration(String),
+    #[error("error reading or writing CSV: {0}")]
+    Csv(#[from] csv::Error),
+    #[error("{0} line(s) could not be imported, nothing was written")]
+    Import(usize),
+    #[error("{0}")]
+    Invalid(String),
+    #[error("{0}")]
+    Usage(String),
+}
+
+/// The on-disk envelope. `version` is bumped whenever the shape of a stored
+/// row changes, and `migrations` knows how to get older files up to date.
+#[derive(Serialize, Deserialize, Clone, Default)]
+pub struct Database {
+    pub version: u64,
+    pub categories: Vec<Category>,
+    pub pets: Vec<Pet>,
+    pub children: Vec<Child>,
+}
+
+/// Which list of the `Database` a command works on.
+#[derive(Serialize, Deserialize, Copy, Clone, Debug, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum Entity {
+    Pets,
+    Children,
+}
+
+impl std::str::FromStr for Entity {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "pet" | "pets" => Ok(Entity::Pets),
+            "child" | "children" => Ok(Entity::Children),
+            other => Err(Error::Usage(format!(
+                "unknown kind '{}', expected 'pets' or 'children'",
+                other
+            ))),
+        }
+    }
+}
+
+impl std::fmt::Display for Entity {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Entity::Pets => write!(f, "pet"),
+            Entity::Children => write!(f, "child"),
+        }
+    }
+}
+
+/// A `DataRow` that lives in its own list inside the `Database`.
+pub trait Stored: DataRow + Serialize + DeserializeOwned + Clone {
+    fn entity() -> Entity;
+    fn rows(db: &Database) -> &Vec<Self>;
+    fn rows_mut(db: &mut Database) -> &mut Vec<Self>;
+    fn deleted_at(&self) -> Option<DateTime<Utc>>;
+    fn set_deleted_at(&mut self, deleted_at: Option<DateTime<Utc>>);
+
+    /// The rows that are not in the trash.
+    fn live(db: &Database) -> Vec<Self> {
+        Self::rows(db)
+            .iter()
+            .filter(|row| row.deleted_at().is_none())
+            .cloned()
+            .collect()
+    }
+
+    /// The rows in the trash, most recently deleted first.
+    fn trashed(db: &Database) -> Vec<Self> {
+        let mut trashed: Vec<Self> = Self::rows(db)
+            .iter()
+            .filter(|row| row.deleted_at().is_some())
+            .cloned()
+            .collect();
+        trashed.sort_by_key(|row| std::cmp::Reverse(row.deleted_at()));
+        trashed
+    }
+
+    /// What is wrong with the row against the rest of the data, for example that its category
+    /// doesn't exist, as in "is in the unknown category 'x'".
+    fn problem(&self, _db: &Database) -> Option<String> {
+        None
+    }
+
+    /// Fails with the `problem` of the row, if it has one.
+    fn validate(&self, db: &Database) -> Result<(), Error> {
+        match self.problem(db) {
+            Some(problem) => Err(Error::Invalid(format!(
+                "{} {} {}",
+                Self::row_name(),
+                self.id(),
+                problem
+            ))),
+            None => Ok(()),
+        }
+    }
+
+    /// Adjusts a freshly made placeholder so that it passes `validate`.
+    fn fit_into(&mut self, _db: &Database) {}
+
+    /// Deals with the rows that refer to the row `id` before it goes to the trash or, with
+    /// `purge`, away for good. An error means the row has to stay where it is.
+    fn release(
+        _db: &mut Database,
+        _id: usize,
+        _on_delete: OnDelete,
+        _purge: bool,
+    ) -> Result<(), Error> {
+        Ok(())
+    }
+
+    fn position(db: &Database, id: usize) -> Option<usize> {
+        Self::rows(db).iter().position(|row| row.id() == id)
+    }
+
+    /// Permanently drops the rows that went into the trash before `cutoff` and hands them back.
+    /// Rows that others still depend on, as `release` decides, stay in the trash.
+    fn purge_trash(db: &mut Database, cutoff: DateTime<Utc>, on_delete: OnDelete) -> Vec<Self> {
+        let expired: Vec<usize> = Self::rows(db)
+            .iter()
+            .filter(|row| row.deleted_at().is_some_and(|at| at < cutoff))
+            .map(DataRow::id)
+            .collect();
+        let mut purged = Vec::new();
+        for id in expired {
+            if Self::release(db, id, on_delete, true).is_err() {
+                continue;
+            }
+            if let Some(idx) = Self::position(db, id) {
+                purged.push(Self::rows_mut(db).remove(idx));
+            }
+        }
+        purged
+    }
+}
+
+impl Database {
+    /// Every write goes through this, so nothing invalid reaches the disk.
+    pub fn validate(&self) -> Result<(), Error> {
+        for pet in &self.pets {
+            pet.validate(self)?;
+        }
+        for child in &self.children {
+            child.validate(self)?;
+        }
+        Ok(())
+    }
+}
+
+impl Stored for Pet {
+    fn entity() -> Entity {
+        Entity::Pets
+    }
+
+    fn rows(db: &Database) -> &Vec<Self> {
+        &db.pets
+    }
+
+    fn rows_mut(db: &mut Database) -> &mut Vec<Self> {
+        &mut db.pets
+    }
+
+    fn problem(&self, db: &Database) -> Option<String> {
+        if !db.has_category(&self.category) {
+            return Some(format!("is in the unknown category '{}'", self.category));
+        }
+        match self.owner {
+            Some(owner) if db.owner_of(self).is_none() => {
+                Some(format!("is owned by child {}, who doesn't exist", owner))
+            }
+            _ => None,
+        }
+    }
+
+    fn fit_into(&mut self, db: &Database) {
+        if let Some(category) = db.categories.choose(&mut rand::thread_rng()) {
+            self.category = category.clone();
+        }
+    }
+
+    fn deleted_at(&self) -> Option<DateTime<Utc>> {
+        self.deleted_at
+    }
+
+    fn set_deleted_at(&mut self, deleted_at: Option<DateTime<Utc>>) {
+        self.deleted_at = deleted_at;
+    }
+}
+
+impl Stored for Child {
+    fn entity() -> Entity {
+        Entity::Children
+    }
+
+    fn rows(db: &Database) -> &Vec<Self> {
+        &db.children
+    }
+
+    fn rows_mut(db: &mut Database) -> &mut Vec<Self> {
+        &mut db.children
+    }
+
+    fn release(
+        db: &mut Database,
+        id: usize,
+        on_delete: OnDelete,
+        purge: bool,
+    ) -> Result<(), Error> {
+        db.release_child(id, on_delete, purge)
+    }
+
+    fn deleted_at(&self) -> Option<DateTime<Utc>> {
+        self.deleted_at
+    }
+
+    fn set_deleted_at(&mut self, deleted_at: Option<DateTime<Utc>>) {
+        self.deleted_at = deleted_at;
+    }
+}
+
+/// The file formats a `Store` can be kept in, picked by the file's extension.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Format {
+    Json,
+    Yaml,
+    Toml,
+}
+
+impl Format {
+    pub fn from_path(path: &Path) -> Result<Self, Error> {
+        match path.extension().and_then(|e| e.to_str()) {
+            Some("json") => Ok(Format::Json),
+            Some("yaml") | Some("yml") => Ok(Format::Yaml),
+            Some("toml") => Ok(Format::Toml),
+            _ => Err(Error::UnknownFormat(path.display().to_string())),
+        }
+    }
+
+    /// Every format parses into a `serde_json::Value` first, so migrations only deal with one shape.
+    pub fn parse(self, content: &str) -> Result<serde_json::Value, Error> {
+        Ok(match self {
+            Format::Json => serde_json::from_str(content)?,
+            Format::Yaml => serde_yaml::from_str(content)?,
+            Format::Toml => toml::from_str(content)?,
+        })
+    }
+
+    /// Pretty-printed, with keys in field declaration order, so the file diffs well.
+    pub fn render(self, db: &Database) -> Result<String, Error> {
+        Ok(match self {
+            Format::Json => serde_json::to_string_pretty(db)? + "\n",
+            Format::Yaml => serde_yaml::to_string(db)?,
+            Format::Toml => toml::to_string_pretty(db)?,
+        })
+    }
+}
+
+/// Where the `Database` is kept on disk, and how its backups are rotated.
+pub struct Store {
+    pub path: PathBuf,
+    pub format: Format,
+    pub backups: BackupConfig,
+}
+
+impl Store {
+    pub fn open<P: Into<PathBuf>>(path: P, backups: BackupConfig) -> Result<Self, Error> {
+        let path = path.into();
+        let format = Format::from_path(&path)?;
+        Ok(Store {
+            path,
+            format,
+            backups,
+        })
+    }
+
+    pub fn read_raw(&self) -> Result<serde_json::Value, Error> {
+        let db_content = fs::read_to_string(&self.path)?;
+        self.format.parse(&db_content)
+    }
+
+    /// Reads the DB file, upgrading it in memory if it was written by an older version.
+    /// The upgraded form only hits the disk on the next write.
+    pub fn read(&self) -> Result<Database, Error> {
+        let raw = self.read_raw()?;
+        let upgraded = migrations::upgrade(raw, &mut Vec::new())?;
+        Ok(serde_json::from_value(upgraded)?)
+    }
+
+    pub fn write(&self, db: &Database) -> Result<(), Error> {
+        db.validate()?;
+        fs::write(&self.path, self.format.render(db)?)?;
+        Ok(())
+    }
+
+    /// For writes that drop or overwrite data: the file as it was goes to the backups first.
+    pub fn write_after_backup(&self, db: &Database) -> Result<(), Error> {
+        backups::take(self)?;
+        self.write(db)
+    }
+}
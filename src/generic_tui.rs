@@ -1,39 +1,62 @@
-use crate::data_row::DataRow;
+use crate::audit::{self, AuditEntry, Logged, Version};
+use crate::backups::{self, Backup, BackupKind};
+use crate::category::Category;
+use crate::data_row::{local_time, AgeUnit, Aged, DataRow, FieldKind, SortKey};
+use crate::date_picker::{DatePicker, Picked};
+use crate::db_stuff::{Database, Entity, Error, Store, Stored};
+use crate::keymap::{Action, Keymap};
+use crate::modal::{Command, Navigation};
+use crate::palette::{Chosen, Entry, Palette};
+use crate::stats::{self, Stats};
+use crate::theme::{Role, Theme, ThemeConfig};
+use crate::upcoming::{self, Reminder};
+use chrono::prelude::*;
 use crossterm::{
-    event::{self, Event as CEvent, KeyCode},
-    terminal::{disable_raw_mode, enable_raw_mode},
+    cursor::Show,
+    event::{
+        self, DisableMouseCapture, EnableMouseCapture, Event as CEvent, KeyCode, KeyEvent,
+        KeyModifiers, MouseButton, MouseEvent, MouseEventKind,
+    },
+    execute,
+    terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
 };
+use std::collections::HashMap;
+use std::convert::TryFrom;
 use std::io;
-use std::sync::mpsc;
+use std::ops::Range;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::Sender;
+use std::sync::Arc;
 use std::thread;
 use std::time::{Duration, Instant};
 use std::{sync::mpsc::Receiver, thread::JoinHandle};
-use thiserror::Error;
 use tui::{
     backend::{Backend, CrosstermBackend},
-    layout::{Alignment, Constraint, Direction, Layout},
-    style::{Color, Modifier, Style},
+    layout::{Alignment, Constraint, Direction, Layout, Rect},
+    style::{Modifier, Style},
+    symbols,
     text::{Span, Spans},
-    widgets::{Block, BorderType, Borders, List, ListItem, ListState, Paragraph, Table, Tabs},
-    Terminal,
+    widgets::{
+        Axis, BarChart, Block, BorderType, Borders, Cell, Chart, Clear, Dataset, GraphType, List,
+        ListItem, ListState, Paragraph, Row, Sparkline, Table, Tabs, Wrap,
+    },
+    Frame, Terminal,
 };
 
-pub const DB_PATH: &str = "./data/db.json";
+pub type WhichRow = usize;
 
-pub(crate) type WhichRow = usize;
-
-#[derive(Error, Debug)]
-pub enum Error {
-    #[error("error reading the DB file: {0}")]
-    ReadDBError(#[from] io::Error),
-    #[error("error parsing the DB file: {0}")]
-    ParseDBError(#[from] serde_json::Error),
-}
-
-#[derive(Copy, Clone, Debug)]
-pub(crate) enum MenuItem {
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum MenuItem {
     Home,
     Data,
+    Trash,
+    History,
+    Categories,
+    Backups,
+    Upcoming,
+    Calendar,
+    Stats,
 }
 
 impl From<MenuItem> for usize {
@@ -41,77 +64,910 @@ impl From<MenuItem> for usize {
         match input {
             MenuItem::Home => 0,
             MenuItem::Data => 1,
+            MenuItem::Trash => 2,
+            MenuItem::History => 3,
+            MenuItem::Categories => 4,
+            MenuItem::Backups => 5,
+            MenuItem::Upcoming => 6,
+            MenuItem::Calendar => 7,
+            MenuItem::Stats => 8,
+        }
+    }
+}
+
+/// What the TUI is showing: the active screen, the rows behind each screen and the selections.
+pub struct AppState<T> {
+    pub active_menu_item: MenuItem,
+    pub data: Vec<T>,
+    pub data_list_state: ListState,
+    pub trash: Vec<T>,
+    pub trash_list_state: ListState,
+    pub history: Vec<AuditEntry>,
+    pub history_list_state: ListState,
+    pub versions: Option<VersionBrowser<T>>,
+    pub as_of: Option<DateTime<Utc>>,
+    pub categories: CategoriesView,
+    pub backups: BackupsView,
+    /// How many days ahead the Upcoming screen looks.
+    pub upcoming_days: u32,
+    /// What ages are counted and shown in.
+    pub age_unit: AgeUnit,
+    pub calendar: CalendarView,
+    pub prompt: Option<Prompt>,
+    pub picker: Option<Picker>,
+    pub date_picker: Option<DatePicker>,
+    pub palette: Option<Palette>,
+    pub status: Option<String>,
+    /// A command or search being typed, shown in place of the footer.
+    pub command_line: Option<String>,
+    pub navigation: Navigation,
+    pub theme: Theme,
+    pub hits: HitAreas,
+    /// How wide lists next to a detail pane are, if not as wide as their entries.
+    pub list_width: Option<u16>,
+    /// How wide the lists of `data` and `trash` have to be for their names, worked out when
+    /// they are loaded rather than for every frame.
+    data_width: u16,
+    trash_width: u16,
+    /// How the data screen is sorted, if not in the order the rows are stored.
+    pub sort: Option<SortKey>,
+    /// What `n` and `N` look for.
+    pub search: Option<String>,
+    /// Where a range selection of data rows started; it ends at the selected row.
+    pub visual: Option<WhichRow>,
+    /// Everything that was last read or written, for looking up related rows.
+    pub db: Database,
+    /// What the Stats and Upcoming screens show, as of the day it was worked out. It is worked
+    /// out when one of them is drawn and kept until the data or the day changes; see `figure`.
+    pub stats: Option<(NaiveDate, Stats)>,
+    pub upcoming: Option<(NaiveDate, Vec<Reminder>)>,
+}
+
+/// A line of text being typed into a popup, see `prompt_input`.
+pub struct Prompt {
+    pub title: String,
+    pub text: String,
+}
+
+/// A list to choose one entry from in a popup, see `pick_from`.
+pub struct Picker {
+    pub title: String,
+    pub options: Vec<String>,
+    pub list_state: ListState,
+}
+
+/// A month of the rows, plotted by one of their timestamp fields, see `render_calendar`.
+pub struct CalendarView {
+    pub field: &'static str,
+    /// The highlighted day; the month around it is shown.
+    pub day: NaiveDate,
+    /// The rows per day by `field`, as positions in the data screen's list; see `index`.
+    pub per_day: HashMap<NaiveDate, Vec<usize>>,
+    /// The selection in the list of the highlighted day's rows.
+    pub list_state: ListState,
+}
+
+impl CalendarView {
+    /// Sorts `rows` into days again, for when they or the plotted field change.
+    pub fn index<T: DataRow>(&mut self, rows: &[T]) {
+        self.per_day.clear();
+        for (idx, row) in rows.iter().enumerate() {
+            if let Some(day) = self.day_of(row) {
+                self.per_day.entry(day).or_default().push(idx);
+            }
+        }
+        let len = self.rows_of_day().len();
+        clamp_selection(&mut self.list_state, len);
+    }
+
+    /// Where the highlighted day's rows are in the data screen's list.
+    pub fn rows_of_day(&self) -> &[usize] {
+        self.per_day.get(&self.day).map_or(&[], Vec::as_slice)
+    }
+
+    /// Where the row selected in the day's list is in the data screen's list.
+    pub fn selected(&self) -> Option<usize> {
+        self.list_state
+            .selected()
+            .and_then(|i| self.rows_of_day().get(i))
+            .copied()
+    }
+
+    pub fn select_next(&mut self) {
+        let len = self.rows_of_day().len();
+        select_next(&mut self.list_state, len);
+    }
+
+    pub fn select_previous(&mut self) {
+        let len = self.rows_of_day().len();
+        select_previous(&mut self.list_state, len);
+    }
+
+    fn go_to(&mut self, day: NaiveDate) {
+        self.day = day;
+        self.list_state.select(None);
+        let len = self.rows_of_day().len();
+        clamp_selection(&mut self.list_state, len);
+    }
+
+    pub fn move_days(&mut self, days: i64) {
+        if let Some(day) = self.day.checked_add_signed(chrono::Duration::days(days)) {
+            self.go_to(day);
+        }
+    }
+
+    pub fn move_months(&mut self, months: i32) {
+        let day = match chrono::Months::new(months.unsigned_abs()) {
+            abs if months < 0 => self.day.checked_sub_months(abs),
+            abs => self.day.checked_add_months(abs),
+        };
+        if let Some(day) = day {
+            self.go_to(day);
+        }
+    }
+
+    /// Switches to the next `Timestamp` field of `T`, plotting `rows` by it.
+    pub fn next_field<T: DataRow>(&mut self, rows: &[T]) {
+        let fields: Vec<&'static str> = T::fields()
+            .iter()
+            .filter(|f| f.kind == FieldKind::Timestamp)
+            .map(|f| f.name)
+            .collect();
+        if let Some(idx) = fields.iter().position(|&f| f == self.field) {
+            self.field = fields[(idx + 1) % fields.len()];
         }
+        self.index(rows);
+    }
+
+    /// The day `row` falls on by the plotted field.
+    pub fn day_of<T: DataRow>(&self, row: &T) -> Option<NaiveDate> {
+        row.timestamp(self.field).map(|at| at.date_naive())
+    }
+}
+
+/// The managed pet categories, each with how many pets are in it.
+pub struct CategoriesView {
+    pub categories: Vec<(Category, usize)>,
+    pub list_state: ListState,
+    /// Set while picking the category the selected one gets merged into.
+    pub merging: Option<Category>,
+}
+
+impl CategoriesView {
+    pub fn refresh(&mut self, db: &Database) {
+        self.categories = db
+            .categories
+            .iter()
+            .map(|c| (c.clone(), db.category_usage(c)))
+            .collect();
+        clamp_selection(&mut self.list_state, self.categories.len());
     }
+
+    pub fn selected(&self) -> Option<&Category> {
+        self.list_state
+            .selected()
+            .and_then(|i| self.categories.get(i))
+            .map(|(category, _)| category)
+    }
+}
+
+/// Earlier states of one row, stepped through with '[' and ']' on the data screen.
+pub struct VersionBrowser<T> {
+    pub id: usize,
+    pub versions: Vec<Version<T>>,
+    pub back: usize,
+}
+
+impl<T: Stored> AppState<T> {
+    pub fn load(store: &Store) -> Result<Self, Error> {
+        let mut state = AppState {
+            active_menu_item: MenuItem::Home,
+            data: Vec::new(),
+            data_list_state: get_data_list_state(),
+            trash: Vec::new(),
+            trash_list_state: get_data_list_state(),
+            history: Vec::new(),
+            history_list_state: get_data_list_state(),
+            versions: None,
+            as_of: None,
+            categories: CategoriesView {
+                categories: Vec::new(),
+                list_state: get_data_list_state(),
+                merging: None,
+            },
+            backups: BackupsView::default(),
+            upcoming_days: 30,
+            age_unit: AgeUnit::Years,
+            calendar: CalendarView {
+                field: T::calendar_field(),
+                day: Utc::now().date_naive(),
+                per_day: HashMap::new(),
+                list_state: ListState::default(),
+            },
+            prompt: None,
+            picker: None,
+            date_picker: None,
+            palette: None,
+            status: None,
+            command_line: None,
+            navigation: Navigation::Keys,
+            theme: Theme::new(&ThemeConfig::default()),
+            hits: HitAreas::default(),
+            list_width: None,
+            data_width: 0,
+            trash_width: 0,
+            sort: None,
+            search: None,
+            visual: None,
+            db: Database::default(),
+            stats: None,
+            upcoming: None,
+        };
+        state.refresh(store.read()?);
+        Ok(state)
+    }
+
+    /// Picks up the rows of a freshly read or written `Database`, keeping the selections in range.
+    pub fn refresh(&mut self, db: Database) {
+        self.db = db;
+        self.load_data();
+        self.trash = T::trashed(&self.db);
+        self.trash_width = list_width(self.trash.iter().map(DataRow::name));
+        self.versions = None;
+        self.categories.refresh(&self.db);
+        self.stats = None;
+        self.upcoming = None;
+        clamp_selection(&mut self.trash_list_state, self.trash.len());
+    }
+
+    /// Takes the live rows out of `db` again, in the order of `sort`.
+    fn load_data(&mut self) {
+        self.data = T::live(&self.db);
+        if let Some(sort) = self.sort {
+            sort.sort(&mut self.data);
+        }
+        self.data_width = list_width(self.data.iter().map(DataRow::name));
+        self.calendar.index(&self.data);
+        clamp_selection(&mut self.data_list_state, self.data.len());
+        self.visual = self.visual.filter(|&start| start < self.data.len());
+    }
+
+    /// Whether the screen shows something that changes as time goes by, like ages or what is
+    /// coming up, and so is worth drawing again every `[tui] tick_ms`.
+    pub fn shows_time(&self) -> bool {
+        matches!(
+            self.active_menu_item,
+            MenuItem::Data | MenuItem::Upcoming | MenuItem::Calendar | MenuItem::Stats
+        )
+    }
+
+    /// Sorts the data screen, keeping the same row selected.
+    pub fn sort_by(&mut self, sort: Option<SortKey>) {
+        let selected_id = self.selected_data().map(DataRow::id);
+        self.sort = sort;
+        self.load_data();
+        let selected = selected_id.and_then(|id| self.data.iter().position(|row| row.id() == id));
+        if selected.is_some() {
+            self.data_list_state.select(selected);
+        }
+    }
+
+    /// Selects the next data row, or the previous one unless `forward`, whose name contains
+    /// `query`, ignoring case and going round at the ends. `false` if there is none.
+    pub fn find(&mut self, query: &str, forward: bool) -> bool {
+        let query = query.to_lowercase();
+        let len = self.data.len();
+        let start = self.data_list_state.selected().unwrap_or(0);
+        let found = (1..=len)
+            .map(|step| {
+                if forward {
+                    (start + step) % len
+                } else {
+                    (start + len - step % len) % len
+                }
+            })
+            .find(|&idx| self.data[idx].name().to_lowercase().contains(&query));
+        if found.is_some() {
+            self.data_list_state.select(found);
+        }
+        found.is_some()
+    }
+
+    /// The data rows marked by a range selection.
+    pub fn visual_range(&self) -> Option<(WhichRow, WhichRow)> {
+        let start = self.visual?;
+        let end = self.data_list_state.selected()?;
+        Some((start.min(end), start.max(end)))
+    }
+
+    /// Selects entry `idx` of the list on the active screen, if it has one.
+    pub fn select_row(&mut self, store: &Store, idx: WhichRow) -> Result<(), Error> {
+        let list_state = match self.active_menu_item {
+            MenuItem::Data => &mut self.data_list_state,
+            MenuItem::Trash => &mut self.trash_list_state,
+            MenuItem::History => &mut self.history_list_state,
+            MenuItem::Categories => &mut self.categories.list_state,
+            MenuItem::Backups => return self.backups.select(store, idx),
+            _ => return Ok(()),
+        };
+        if list_state.selected().is_some() {
+            list_state.select(Some(idx));
+        }
+        Ok(())
+    }
+
+    /// The selected entry of the list on the active screen.
+    pub fn selected_row(&self) -> Option<WhichRow> {
+        match self.active_menu_item {
+            MenuItem::Data => self.data_list_state.selected(),
+            MenuItem::Trash => self.trash_list_state.selected(),
+            MenuItem::History => self.history_list_state.selected(),
+            MenuItem::Categories => self.categories.list_state.selected(),
+            MenuItem::Backups => self.backups.list_state.selected(),
+            _ => None,
+        }
+    }
+
+    /// How many entries the list on the active screen has.
+    pub fn list_len(&self) -> usize {
+        match self.active_menu_item {
+            MenuItem::Data => self.data.len(),
+            MenuItem::Trash => self.trash.len(),
+            MenuItem::History => self.history.len(),
+            MenuItem::Categories => self.categories.categories.len(),
+            MenuItem::Backups => self.backups.backups.len(),
+            _ => 0,
+        }
+    }
+
+    /// Shows the outcome of a change: the new data, or why it was refused.
+    pub fn apply(&mut self, changed: Result<Database, Error>) {
+        match changed {
+            Ok(db) => self.refresh(db),
+            Err(e) => self.status = Some(e.to_string()),
+        }
+    }
+
+    /// Like `apply`, for a change that was logged. Data that was written is shown even when
+    /// logging it failed, which the status line then says.
+    pub fn apply_logged(&mut self, changed: Result<Logged, Error>) {
+        match changed {
+            Ok(Logged { db, log }) => {
+                self.refresh(db);
+                if let Err(e) = log {
+                    self.status = Some(format!("saved, but not logged: {}", e));
+                }
+            }
+            Err(e) => self.status = Some(e.to_string()),
+        }
+    }
+
+    pub fn selected_data(&self) -> Option<&T> {
+        self.data_list_state
+            .selected()
+            .and_then(|i| self.data.get(i))
+    }
+
+    pub fn selected_trash(&self) -> Option<&T> {
+        self.trash_list_state
+            .selected()
+            .and_then(|i| self.trash.get(i))
+    }
+
+    /// The version browser, if it is open on the selected row.
+    pub fn browsed_version(&self) -> Option<&VersionBrowser<T>> {
+        let selected_id = self.selected_data().map(DataRow::id);
+        self.versions
+            .as_ref()
+            .filter(|browser| Some(browser.id) == selected_id && browser.back > 0)
+    }
+
+    pub fn older_version(&mut self, store: &Store) -> Result<(), Error> {
+        let row = match self.selected_data() {
+            Some(row) => row,
+            None => return Ok(()),
+        };
+        if self.versions.as_ref().map(|b| b.id) != Some(row.id()) {
+            self.versions = Some(VersionBrowser {
+                id: row.id(),
+                versions: audit::versions(store, row, self.as_of)?,
+                back: 0,
+            });
+        }
+        if let Some(browser) = self.versions.as_mut() {
+            browser.back = (browser.back + 1).min(browser.versions.len() - 1);
+        }
+        Ok(())
+    }
+
+    pub fn newer_version(&mut self) {
+        if let Some(browser) = self.versions.as_mut() {
+            browser.back = browser.back.saturating_sub(1);
+        }
+    }
+
+    /// Shows the data as it was at `at`. Nothing can be changed until `back_to_live`.
+    pub fn view_as_of(&mut self, store: &Store, at: DateTime<Utc>) -> Result<(), Error> {
+        let past = audit::as_of(store, &store.read()?, at)?;
+        self.as_of = Some(at);
+        self.versions = None;
+        self.refresh(past);
+        Ok(())
+    }
+
+    pub fn back_to_live(&mut self, store: &Store) -> Result<(), Error> {
+        self.as_of = None;
+        self.versions = None;
+        self.refresh(store.read()?);
+        Ok(())
+    }
+
+    /// The logged changes of the selected row, newest first. A log that can't be read, or
+    /// only partly, is reported on the status line.
+    pub fn load_history(&mut self, store: &Store) {
+        let log = match self.selected_data() {
+            Some(row) => audit::history(store, Some(T::entity()), row.id()),
+            None => Ok(Default::default()),
+        };
+        let log = log.unwrap_or_else(|e| {
+            self.status = Some(e.to_string());
+            Default::default()
+        });
+        if log.skipped > 0 {
+            self.status = Some(format!(
+                "skipped {} unreadable line(s) of the audit log",
+                log.skipped
+            ));
+        }
+        self.history = log.entries;
+        self.history.reverse();
+        self.history_list_state.select(Some(0));
+        clamp_selection(&mut self.history_list_state, self.history.len());
+    }
+
+    /// What a click or a turn of the wheel asks for: clicking a tab runs its action, clicking a
+    /// row selects it and double-clicking it opens it, the wheel moves the selection.
+    pub fn mouse(&mut self, mouse: &MouseEvent) -> Vec<Command> {
+        let at = (mouse.column, mouse.row);
+        match mouse.kind {
+            MouseEventKind::ScrollDown => vec![Command::Run(Action::Down)],
+            MouseEventKind::ScrollUp => vec![Command::Run(Action::Up)],
+            MouseEventKind::Down(MouseButton::Left) => {
+                let double = self.hits.double_click(at);
+                if let Some(action) = self.hits.tab_at(at) {
+                    return vec![Command::Run(action)];
+                }
+                let screen = self.active_menu_item;
+                let row = match self.hits.row_at(screen, at) {
+                    Some(row) if row < self.list_len() => row,
+                    _ => return Vec::new(),
+                };
+                let mut commands = vec![Command::GoTo(row + 1)];
+                match screen {
+                    MenuItem::Data | MenuItem::Categories if double => {
+                        commands.push(Command::Run(Action::Edit))
+                    }
+                    MenuItem::History if double => commands.push(Command::Run(Action::Confirm)),
+                    _ => {}
+                }
+                commands
+            }
+            _ => Vec::new(),
+        }
+    }
+}
+
+impl<T: Stored + Aged> AppState<T> {
+    /// Works out what the active screen shows as of `today`, unless that is known already.
+    pub fn figure(&mut self, today: NaiveDate) {
+        let known = |day: Option<NaiveDate>| day == Some(today);
+        match self.active_menu_item {
+            MenuItem::Stats if !known(self.stats.as_ref().map(|(day, _)| *day)) => {
+                self.stats = Some((today, Stats::of(&self.data, today, self.age_unit)));
+            }
+            MenuItem::Upcoming if !known(self.upcoming.as_ref().map(|(day, _)| *day)) => {
+                let reminders = upcoming::upcoming(&self.db, today, self.upcoming_days);
+                self.upcoming = Some((today, reminders));
+            }
+            _ => {}
+        }
+    }
+}
+
+/// The backups of the data file, and how the selected one differs from the current data.
+/// Empty until the Backups screen loads it.
+#[derive(Default)]
+pub struct BackupsView {
+    pub backups: Vec<Backup>,
+    pub list_state: ListState,
+    pub diff: Vec<String>,
+}
+
+impl BackupsView {
+    pub fn load(store: &Store) -> Result<Self, Error> {
+        let mut view = BackupsView {
+            backups: backups::list(store)?,
+            list_state: ListState::default(),
+            diff: Vec::new(),
+        };
+        view.select(store, 0)?;
+        Ok(view)
+    }
+
+    pub fn select(&mut self, store: &Store, idx: WhichRow) -> Result<(), Error> {
+        self.diff.clear();
+        if self.backups.is_empty() {
+            self.list_state.select(None);
+            return Ok(());
+        }
+        let idx = idx.min(self.backups.len() - 1);
+        self.list_state.select(Some(idx));
+        self.diff = backups::diff(&self.backups[idx].read()?, &store.read()?);
+        Ok(())
+    }
+
+    pub fn select_next(&mut self, store: &Store) -> Result<(), Error> {
+        match self.list_state.selected() {
+            Some(selected) if selected + 1 < self.backups.len() => self.select(store, selected + 1),
+            _ => self.select(store, 0),
+        }
+    }
+
+    pub fn select_previous(&mut self, store: &Store) -> Result<(), Error> {
+        match self.list_state.selected() {
+            Some(selected) if selected > 0 => self.select(store, selected - 1),
+            _ => self.select(store, self.backups.len().saturating_sub(1)),
+        }
+    }
+
+    pub fn selected(&self) -> Option<&Backup> {
+        self.list_state.selected().and_then(|i| self.backups.get(i))
+    }
+}
+
+/// Where the tabs and the list of the active screen were drawn last, to tell what a click hit.
+#[derive(Default)]
+pub struct HitAreas {
+    tabs: Vec<(Rect, Action)>,
+    /// The rows of the list on the active screen.
+    list: Option<Rect>,
+    /// The row at the top of each screen's list, by `MenuItem` index.
+    offsets: HashMap<usize, usize>,
+    /// When and where the left button last went down.
+    last_click: Option<(Instant, (u16, u16))>,
+    /// How wide the list next to a detail pane was drawn.
+    list_width: Option<u16>,
+}
+
+const DOUBLE_CLICK: Duration = Duration::from_millis(400);
+
+impl HitAreas {
+    /// Forgets where things were, before they are drawn again.
+    fn clear(&mut self) {
+        self.tabs.clear();
+        self.list = None;
+    }
+
+    /// Notes where the tabs go in `area`, laid out the way `Tabs` lays them out: a space on
+    /// either side of each title and a one-column divider between them.
+    fn tabs(&mut self, area: Rect, tabs: &[(Action, Spans)]) {
+        let inner = Block::default().borders(Borders::ALL).inner(area);
+        let mut x = inner.left();
+        for (action, title) in tabs {
+            x = x.saturating_add(1);
+            let width = u16::try_from(title.width()).unwrap_or(u16::MAX);
+            let width = width.min(inner.right().saturating_sub(x));
+            self.tabs
+                .push((Rect::new(x, inner.top(), width, 1), *action));
+            x = x.saturating_add(width + 2);
+        }
+    }
+
+    /// Notes that the list of `screen` goes in `area` with `selected` selected, and returns the
+    /// entry at the top of it. `List` scrolls as little as it can to keep the selection in
+    /// view, and so does this.
+    fn list(&mut self, screen: MenuItem, area: Rect, selected: Option<WhichRow>) -> WhichRow {
+        let inner = Block::default().borders(Borders::ALL).inner(area);
+        let height = usize::from(inner.height.max(1));
+        let offset = self.offsets.entry(screen.into()).or_insert(0);
+        let selected = selected.unwrap_or(0);
+        if selected >= *offset + height {
+            *offset = selected + 1 - height;
+        } else if selected < *offset {
+            *offset = selected;
+        }
+        self.list = Some(inner);
+        *offset
+    }
+
+    /// How many entries of the list on the active screen are in view at once.
+    pub fn page(&self) -> usize {
+        self.list.map_or(1, |list| usize::from(list.height.max(1)))
+    }
+
+    fn tab_at(&self, (column, row): (u16, u16)) -> Option<Action> {
+        self.tabs
+            .iter()
+            .find(|(rect, _)| contains(*rect, column, row))
+            .map(|(_, action)| *action)
+    }
+
+    fn row_at(&self, screen: MenuItem, (column, row): (u16, u16)) -> Option<WhichRow> {
+        let list = self.list.filter(|list| contains(*list, column, row))?;
+        let offset = self.offsets.get(&screen.into()).copied().unwrap_or(0);
+        Some(offset + usize::from(row - list.top()))
+    }
+
+    fn panes(&mut self, panes: &Panes) {
+        if !panes.stacked {
+            self.list_width = Some(panes.list.width);
+        }
+    }
+
+    pub fn list_width(&self) -> Option<u16> {
+        self.list_width
+    }
+
+    /// Whether a click at `at` follows one at the same spot quickly enough to make a double-click.
+    fn double_click(&mut self, at: (u16, u16)) -> bool {
+        let now = Instant::now();
+        let double = matches!(self.last_click, Some((then, spot)) if spot == at && now - then < DOUBLE_CLICK);
+        self.last_click = if double { None } else { Some((now, at)) };
+        double
+    }
+}
+
+fn contains(rect: Rect, column: u16, row: u16) -> bool {
+    (rect.left()..rect.right()).contains(&column) && (rect.top()..rect.bottom()).contains(&row)
 }
 
 pub enum Event<I> {
     Input(I),
+    Mouse(MouseEvent),
+    /// Text that arrived all at once rather than key by key, as it does when it is pasted.
+    Paste(String),
+    /// The terminal is now this many columns wide and rows tall.
+    Resize(u16, u16),
+    /// Something the program itself noticed, see `AppEvent`.
+    App(AppEvent),
+    /// `[tui] tick_ms` went by without anything happening while `AppState::shows_time`.
     Tick,
+    /// The terminal could not be read, nothing more comes from it.
+    Failed(crossterm::ErrorKind),
 }
 
-pub(crate) fn io_handler() -> (Receiver<Event<event::KeyEvent>>, JoinHandle<()>) {
-    enable_raw_mode().expect("can run in raw mode");
-    let (tx, rx) = mpsc::channel();
-    let tick_rate = Duration::from_millis(200);
+/// Events that come from the program rather than the terminal.
+pub enum AppEvent {
+    /// The data file was written to, by this program or another.
+    DataChanged,
+}
 
-    let jh: JoinHandle<()> = thread::spawn(move || {
-        let mut last_tick = Instant::now();
-        loop {
-            let timeout = tick_rate
-                .checked_sub(last_tick.elapsed())
-                .unwrap_or_else(|| Duration::from_secs(0));
+/// A thread of the TUI that runs until it is stopped.
+pub struct Worker {
+    stop: Arc<AtomicBool>,
+    handle: JoinHandle<()>,
+}
+
+impl Worker {
+    /// Runs `work` on a new thread, which is to return once the flag it gets is set.
+    fn spawn<F: FnOnce(&AtomicBool) + Send + 'static>(work: F) -> Self {
+        let stop = Arc::new(AtomicBool::new(false));
+        let flag = Arc::clone(&stop);
+        Worker {
+            stop,
+            handle: thread::spawn(move || work(&flag)),
+        }
+    }
 
-            if event::poll(timeout).expect("poll works") {
-                if let CEvent::Key(key) = event::read().expect("can read events") {
-                    tx.send(Event::Input(key)).expect("can send events");
+    /// Tells the thread to stop, wakes it if it is asleep and waits until it is done.
+    pub fn stop(self) {
+        self.stop.store(true, Ordering::Relaxed);
+        self.handle.thread().unpark();
+        // A thread that panicked has already said so, there is nothing left to clean up.
+        let _ = self.handle.join();
+    }
+}
+
+/// How long the terminal thread waits for input before it looks whether it is to stop.
+const STOP_CHECK: Duration = Duration::from_millis(200);
+
+/// Reads the terminal on a thread of its own and sends what happens there to `tx`, until it is
+/// stopped or reading fails, which it sends as `Event::Failed`.
+pub fn io_handler(tx: Sender<Event<KeyEvent>>) -> Worker {
+    Worker::spawn(move |stop| {
+        while !stop.load(Ordering::Relaxed) {
+            let events = match event::poll(STOP_CHECK) {
+                Ok(false) => continue,
+                Ok(true) => read_events(),
+                Err(e) => Err(e),
+            };
+            match events {
+                Ok(events) => {
+                    for event in events {
+                        let _ = tx.send(event);
+                    }
+                }
+                Err(e) => {
+                    let _ = tx.send(Event::Failed(e));
+                    return;
                 }
             }
+        }
+    })
+}
+
+/// Reads the event that is waiting, along with any characters queued right behind it.
+fn read_events() -> crossterm::Result<Vec<Event<KeyEvent>>> {
+    // crossterm 0.19 has no bracketed paste, but pasted text arrives all at once where typed
+    // keys come one at a time, so characters that are already waiting behind the first one
+    // were pasted.
+    let mut typed = Vec::new();
+    let mut next = Some(event::read()?);
+    while let Some(CEvent::Key(key)) = next {
+        if pasted_char(&key).is_none() {
+            break;
+        }
+        typed.push(key);
+        next = match event::poll(Duration::from_secs(0))? {
+            true => Some(event::read()?),
+            false => None,
+        };
+    }
+    let mut events = Vec::new();
+    if typed.len() > 1 {
+        events.push(Event::Paste(typed.iter().filter_map(pasted_char).collect()));
+    } else if let Some(key) = typed.pop() {
+        events.push(Event::Input(key));
+    }
+    events.extend(match next {
+        Some(CEvent::Key(key)) => Some(Event::Input(key)),
+        Some(CEvent::Mouse(mouse)) => Some(Event::Mouse(mouse)),
+        Some(CEvent::Resize(columns, rows)) => Some(Event::Resize(columns, rows)),
+        None => None,
+    });
+    Ok(events)
+}
 
-            if last_tick.elapsed() >= tick_rate {
-                #[allow(clippy::collapsible_if)]
-                if tx.send(Event::Tick).is_ok() {
-                    last_tick = Instant::now();
+/// The character `key` stands for in pasted text: what it types, or a line break for Enter.
+/// Shortcuts aren't part of pasted text.
+fn pasted_char(key: &KeyEvent) -> Option<char> {
+    if key
+        .modifiers
+        .intersects(KeyModifiers::CONTROL | KeyModifiers::ALT)
+    {
+        return None;
+    }
+    match key.code {
+        KeyCode::Char(c) => Some(c),
+        KeyCode::Enter => Some('\n'),
+        _ => None,
+    }
+}
+
+/// Watches the file at `path` on a thread of its own and sends `AppEvent::DataChanged` to `tx`
+/// whenever it is written, until it is stopped or nobody listens anymore.
+pub fn watch_file(path: PathBuf, tx: Sender<Event<KeyEvent>>) -> Worker {
+    let modified = move || path.metadata().and_then(|meta| meta.modified()).ok();
+    Worker::spawn(move |stop| {
+        let mut last = modified();
+        loop {
+            // `Worker::stop` cuts the nap short.
+            thread::park_timeout(Duration::from_secs(1));
+            if stop.load(Ordering::Relaxed) {
+                break;
+            }
+            let now = modified();
+            if now != last {
+                last = now;
+                if tx.send(Event::App(AppEvent::DataChanged)).is_err() {
+                    break;
                 }
             }
         }
-    });
-    (rx, jh)
+    })
 }
 
-pub(crate) fn get_terminal(
+/// The terminal to draw on, in raw mode on a screen of its own, reporting clicks and the mouse
+/// wheel if `mouse` is set. `tui_cleanup` puts it back the way it was, and so does a panic.
+pub fn get_terminal(
+    mouse: bool,
 ) -> Result<Terminal<CrosstermBackend<std::io::Stdout>>, Box<dyn std::error::Error>> {
-    let stdout = io::stdout();
+    let mut stdout = io::stdout();
+    enable_raw_mode()?;
+    execute!(stdout, EnterAlternateScreen)?;
+    if mouse {
+        execute!(stdout, EnableMouseCapture)?;
+    }
+    let panic_hook = std::panic::take_hook();
+    std::panic::set_hook(Box::new(move |info| {
+        let _ = restore_terminal();
+        panic_hook(info);
+    }));
     let backend = CrosstermBackend::new(stdout);
     let mut terminal = Terminal::new(backend)?;
     terminal.clear()?;
     Ok(terminal)
 }
 
-pub(crate) fn get_data_list_state() -> ListState {
+/// Leaves raw mode and the screen of the TUI, showing what was on the terminal before.
+fn restore_terminal() -> crossterm::Result<()> {
+    disable_raw_mode()?;
+    execute!(
+        io::stdout(),
+        DisableMouseCapture,
+        LeaveAlternateScreen,
+        Show
+    )
+}
+
+pub fn get_data_list_state() -> ListState {
     let mut data_list_state = ListState::default();
     data_list_state.select(Some(0));
     data_list_state
 }
 
-pub(crate) fn tui_cleanup<B: Backend>(
+pub fn clamp_selection(list_state: &mut ListState, len: usize) {
+    match list_state.selected() {
+        _ if len == 0 => list_state.select(None),
+        Some(selected) if selected >= len => list_state.select(Some(len - 1)),
+        None => list_state.select(Some(0)),
+        _ => {}
+    }
+}
+
+pub fn select_next(list_state: &mut ListState, len: usize) {
+    if let Some(selected) = list_state.selected() {
+        if selected + 1 >= len {
+            list_state.select(Some(0));
+        } else {
+            list_state.select(Some(selected + 1));
+        }
+    }
+}
+
+pub fn select_previous(list_state: &mut ListState, len: usize) {
+    if let Some(selected) = list_state.selected() {
+        if selected > 0 {
+            list_state.select(Some(selected - 1));
+        } else {
+            list_state.select(Some(len.saturating_sub(1)));
+        }
+    }
+}
+
+pub fn tui_cleanup<B: Backend>(
     terminal: &mut Terminal<B>,
 ) -> Result<(), Box<dyn std::error::Error>> {
-    disable_raw_mode()?;
-    terminal.clear()?;
+    restore_terminal()?;
     terminal.show_cursor()?;
     Ok(())
 }
 
-pub(crate) fn render<T: DataRow, B: Backend>(
+pub fn render<T: Stored + Aged, B: Backend>(
     terminal: &mut Terminal<B>,
-    menu_titles: &[&str],
-    active_menu_item: MenuItem,
-    data_list_state: &mut ListState,
-    loaded_data: &[T],
+    keymap: &Keymap,
+    state: &mut AppState<T>,
 ) -> Result<(), Box<dyn std::error::Error>> {
+    // What is coming up counts from the day it is where the user is.
+    state.figure(Local::now().date_naive());
     terminal.draw(|rect| {
         let size = rect.size();
+        let theme = &state.theme;
+        state.hits.clear();
+        if size.width < MIN_WIDTH || size.height < MIN_HEIGHT {
+            let notice = Paragraph::new(vec![
+                Spans::from(Span::styled("Terminal too small", theme.style(Role::Error))),
+                Spans::from(format!(
+                    "{}x{}, needs {}x{}",
+                    size.width, size.height, MIN_WIDTH, MIN_HEIGHT
+                )),
+            ])
+            .alignment(Alignment::Center)
+            .wrap(Wrap { trim: true });
+            let top = size.height.saturating_sub(2) / 2;
+            rect.render_widget(notice, Rect::new(0, top, size.width, size.height - top));
+            return;
+        }
         let chunks = Layout::default()
             .direction(Direction::Vertical)
             .margin(2)
@@ -125,164 +981,1262 @@ pub(crate) fn render<T: DataRow, B: Backend>(
             )
             .split(size);
 
-        let copyright = Paragraph::new(T::cli_name() + " 2020 - all rights reserved")
-            .style(Style::default().fg(Color::LightCyan))
+        let (footer, footer_color) = match (&state.command_line, &state.status) {
+            (Some(command_line), _) => (command_line.clone(), Role::Text),
+            (None, Some(status)) => (status.clone(), Role::Error),
+            (None, None) => (T::cli_name() + " 2020 - all rights reserved", Role::Info),
+        };
+        let copyright = Paragraph::new(ellipsize(&footer, chunks[2].width.saturating_sub(2)))
+            .style(theme.style(footer_color))
             .alignment(Alignment::Center)
             .block(
                 Block::default()
                     .borders(Borders::ALL)
-                    .style(Style::default().fg(Color::White))
+                    .style(theme.style(Role::Text))
                     .title("Copyright")
                     .border_type(BorderType::Plain),
             );
 
-        let menu = menu_titles
-            .iter()
-            .map(|t| {
-                let (first, rest) = t.split_at(1);
-                Spans::from(vec![
-                    Span::styled(
-                        first,
-                        Style::default()
-                            .fg(Color::Yellow)
-                            .add_modifier(Modifier::UNDERLINED),
-                    ),
-                    Span::styled(rest, Style::default().fg(Color::White)),
-                ])
-            })
+        let tabs: Vec<_> = menu_tabs::<T>()
+            .into_iter()
+            .map(|(action, title)| (action, menu_tab(keymap, action, title, theme)))
             .collect();
+        state.hits.tabs(chunks[0], &tabs);
+        let screen = screen_action::<T>(state.active_menu_item);
+        let selected = tabs.iter().position(|(action, _)| *action == screen);
+        let menu = tabs.into_iter().map(|(_, title)| title).collect();
 
+        let menu_title = match state.as_of {
+            Some(at) => format!("Menu - read-only, as of {} (Esc for the live data)", at),
+            None => "Menu".to_owned(),
+        };
         let tabs = Tabs::new(menu)
-            .select(active_menu_item.into())
-            .block(Block::default().title("Menu").borders(Borders::ALL))
-            .style(Style::default().fg(Color::White))
-            .highlight_style(Style::default().fg(Color::Yellow))
+            .select(selected.unwrap_or(0))
+            .block(
+                Block::default()
+                    .title(ellipsize(&menu_title, chunks[0].width.saturating_sub(2)))
+                    .borders(Borders::ALL),
+            )
+            .style(theme.style(Role::Text))
+            .highlight_style(theme.style(Role::Accent))
             .divider(Span::raw("|"));
 
         rect.render_widget(tabs, chunks[0]);
-        match active_menu_item {
-            MenuItem::Home => rect.render_widget(render_home::<T>(), chunks[1]),
+        match state.active_menu_item {
+            MenuItem::Home => {
+                rect.render_widget(render_home::<T>(keymap, state.navigation, theme), chunks[1])
+            }
             MenuItem::Data => {
-                let data_chunks = Layout::default()
-                    .direction(Direction::Horizontal)
-                    .constraints([Constraint::Percentage(20), Constraint::Percentage(80)].as_ref())
-                    .split(chunks[1]);
-                let (left, right) = render_data(data_list_state, loaded_data);
-                rect.render_stateful_widget(left, data_chunks[0], data_list_state);
-                rect.render_widget(right, data_chunks[1]);
+                let width = state.list_width.unwrap_or(state.data_width);
+                let panes = Panes::new(chunks[1], width, state.data.len());
+                state.hits.panes(&panes);
+                let mut title = match state.as_of {
+                    Some(at) => format!("{} as of {}", T::title(), at.format("%Y-%m-%d %H:%M")),
+                    None => T::title().to_owned(),
+                };
+                if let Some(sort) = state.sort {
+                    title = format!("{} by {}", title, sort);
+                }
+                let selected = state.data_list_state.selected();
+                let top = state.hits.list(MenuItem::Data, panes.list, selected);
+                let rows = top..top + state.hits.page();
+                let marked = state.visual_range();
+                let list = render_data(&title, &state.data, rows, marked, theme, panes.list.width);
+                draw_window(rect, list, panes.list, selected, top);
+                draw_scrollbar(rect, panes.list, state.data.len(), top, theme);
+                if let Some(area) = panes.detail {
+                    let (fields, block) = match (state.browsed_version(), state.selected_data()) {
+                        (Some(browser), _) => {
+                            let idx = browser.versions.len() - 1 - browser.back;
+                            let (since, version) = &browser.versions[idx];
+                            let since = match since {
+                                Some(at) => format!("from {}", at.format("%Y-%m-%d %H:%M:%S")),
+                                None => "from before logging started".to_owned(),
+                            };
+                            let block = Block::default()
+                                .borders(Borders::ALL)
+                                .style(theme.style(Role::Accent))
+                                .title(format!(
+                                    "Version {} of {}, {} ('[' older, ']' newer)",
+                                    idx + 1,
+                                    browser.versions.len(),
+                                    since
+                                ))
+                                .border_type(BorderType::Plain);
+                            (version.detail(&state.db, state.age_unit), block)
+                        }
+                        (None, row) => {
+                            let title = match row {
+                                Some(_) => "Detail".to_owned(),
+                                None => format!("No {}", T::title().to_lowercase()),
+                            };
+                            let block = Block::default()
+                                .borders(Borders::ALL)
+                                .style(theme.style(Role::Text))
+                                .title(title)
+                                .border_type(BorderType::Plain);
+                            (
+                                row.map(|row| row.detail(&state.db, state.age_unit))
+                                    .unwrap_or_default(),
+                                block,
+                            )
+                        }
+                    };
+                    draw_detail(rect, area, &fields, block, theme);
+                }
+            }
+            MenuItem::Trash => {
+                let width = state.list_width.unwrap_or(state.trash_width);
+                let panes = Panes::new(chunks[1], width, state.trash.len());
+                state.hits.panes(&panes);
+                let selected = state.trash_list_state.selected();
+                let top = state.hits.list(MenuItem::Trash, panes.list, selected);
+                let rows = top..top + state.hits.page();
+                let list = render_data("Trash", &state.trash, rows, None, theme, panes.list.width);
+                draw_window(rect, list, panes.list, selected, top);
+                draw_scrollbar(rect, panes.list, state.trash.len(), top, theme);
+                if let Some(area) = panes.detail {
+                    let row = state.selected_trash();
+                    let title = match row.map(|row| (row, row.deleted_at())) {
+                        Some((_, Some(deleted_at))) => {
+                            format!(
+                                "Deleted {} ('r' to restore, 'd' to purge)",
+                                local_time(&deleted_at)
+                            )
+                        }
+                        Some((_, None)) => "Detail".to_owned(),
+                        None => "No trash".to_owned(),
+                    };
+                    let block = Block::default()
+                        .borders(Borders::ALL)
+                        .style(theme.style(Role::Text))
+                        .title(title)
+                        .border_type(BorderType::Plain);
+                    let fields = row
+                        .map(|row| row.detail(&state.db, state.age_unit))
+                        .unwrap_or_default();
+                    draw_detail(rect, area, &fields, block, theme);
+                }
+            }
+            MenuItem::History => {
+                let title = match state.selected_data() {
+                    Some(row) => format!("History of {} ({})", row.name(), row.id()),
+                    None => "History".to_owned(),
+                };
+                let history = render_history(&title, &state.history, theme);
+                let selected = state.history_list_state.selected();
+                let top = state.hits.list(MenuItem::History, chunks[1], selected);
+                rect.render_stateful_widget(history, chunks[1], &mut state.history_list_state);
+                draw_scrollbar(rect, chunks[1], state.history.len(), top, theme);
+            }
+            MenuItem::Categories => {
+                let categories = &state.categories.categories;
+                let entries = categories
+                    .iter()
+                    .map(|(category, count)| format!("{} ({})", category, count));
+                let width = state.list_width.unwrap_or_else(|| list_width(entries));
+                let panes = Panes::new(chunks[1], width, categories.len());
+                state.hits.panes(&panes);
+                let mut list_state = state.categories.list_state.clone();
+                let (left, right) = render_categories(&state.categories, theme);
+                let selected = list_state.selected();
+                let top = state.hits.list(MenuItem::Categories, panes.list, selected);
+                rect.render_stateful_widget(left, panes.list, &mut list_state);
+                let len = state.categories.categories.len();
+                draw_scrollbar(rect, panes.list, len, top, theme);
+                if let Some(area) = panes.detail {
+                    rect.render_widget(right, area);
+                }
+            }
+            MenuItem::Upcoming => {
+                if let Some((today, reminders)) = &state.upcoming {
+                    // The table doesn't scroll, so only the reminders that fit are laid out:
+                    // all but the borders and the header.
+                    let fits = usize::from(chunks[1].height.saturating_sub(3));
+                    let shown = &reminders[..reminders.len().min(fits)];
+                    rect.render_widget(
+                        render_upcoming(shown, *today, state.upcoming_days, theme),
+                        chunks[1],
+                    );
+                }
+            }
+            MenuItem::Calendar => {
+                // Six weeks of two lines each and the names of the days.
+                let panes = Panes::new(chunks[1], chunks[1].width * 2 / 3, 13);
+                let (grid, day_list) = render_calendar(&state.calendar, &state.data, theme);
+                rect.render_widget(grid, panes.list);
+                if let Some(area) = panes.detail {
+                    rect.render_stateful_widget(day_list, area, &mut state.calendar.list_state);
+                }
+            }
+            MenuItem::Stats => {
+                if let Some((_, stats)) = &state.stats {
+                    let rows = Layout::default()
+                        .direction(Direction::Vertical)
+                        .constraints(
+                            [Constraint::Percentage(60), Constraint::Percentage(40)].as_ref(),
+                        )
+                        .split(chunks[1]);
+                    let top = Layout::default()
+                        .direction(Direction::Horizontal)
+                        .constraints(
+                            [Constraint::Percentage(50), Constraint::Percentage(50)].as_ref(),
+                        )
+                        .split(rows[0]);
+                    let per_group: Vec<(&str, u64)> = stats
+                        .per_group
+                        .iter()
+                        .map(|(group, count)| (group.as_str(), *count))
+                        .collect();
+                    let age_points = age_points(&stats.per_age);
+                    let dataset = [Dataset::default()
+                        .name(T::title())
+                        .marker(symbols::Marker::Braille)
+                        .graph_type(GraphType::Line)
+                        .style(theme.style(Role::Info))
+                        .data(&age_points)];
+                    rect.render_widget(render_group_chart::<T>(&per_group, theme), top[0]);
+                    rect.render_widget(
+                        render_age_chart::<T>(stats, state.age_unit, &dataset, theme),
+                        top[1],
+                    );
+                    rect.render_widget(render_month_sparkline::<T>(stats, theme), rows[1]);
+                }
+            }
+            MenuItem::Backups => {
+                let backups = &state.backups.backups;
+                let width = state
+                    .list_width
+                    .unwrap_or_else(|| list_width(backups.iter().map(backup_label)));
+                let panes = Panes::new(chunks[1], width, backups.len());
+                state.hits.panes(&panes);
+                let mut list_state = state.backups.list_state.clone();
+                let (left, right) = render_backups(&state.backups, theme);
+                let selected = list_state.selected();
+                let top = state.hits.list(MenuItem::Backups, panes.list, selected);
+                rect.render_stateful_widget(left, panes.list, &mut list_state);
+                let len = state.backups.backups.len();
+                draw_scrollbar(rect, panes.list, len, top, theme);
+                if let Some(area) = panes.detail {
+                    rect.render_widget(right, area);
+                }
+                state.backups.list_state = list_state;
             }
         }
         rect.render_widget(copyright, chunks[2]);
+        if let Some(prompt) = &state.prompt {
+            let area = centered_rect(60, 3, size);
+            let input = Paragraph::new(prompt.text.clone() + "_").block(
+                Block::default()
+                    .borders(Borders::ALL)
+                    .style(theme.style(Role::Accent))
+                    .title(format!(
+                        "{} (Enter to confirm, Esc to cancel)",
+                        prompt.title
+                    ))
+                    .border_type(BorderType::Plain),
+            );
+            rect.render_widget(Clear, area);
+            rect.render_widget(input, area);
+        }
+        if let Some(date_picker) = &state.date_picker {
+            let area = centered_rect(70, 6, size);
+            rect.render_widget(Clear, area);
+            rect.render_widget(date_picker.render(theme), area);
+        }
+        if let Some(palette) = &state.palette {
+            let area = centered_rect(80, 20, size);
+            let chunks = Layout::default()
+                .direction(Direction::Vertical)
+                .constraints([Constraint::Length(3), Constraint::Min(1)].as_ref())
+                .split(area);
+            let (input, list) = palette.render(theme);
+            rect.render_widget(Clear, area);
+            rect.render_widget(input, chunks[0]);
+            rect.render_stateful_widget(list, chunks[1], &mut palette.list_state().clone());
+        }
+        if let Some(picker) = &state.picker {
+            let rows = u16::try_from(picker.options.len()).unwrap_or(u16::MAX);
+            let area = centered_rect(50, rows.saturating_add(2), size);
+            let items: Vec<_> = picker
+                .options
+                .iter()
+                .map(|option| ListItem::new(Spans::from(vec![Span::raw(option.clone())])))
+                .collect();
+            let list = List::new(items)
+                .block(
+                    Block::default()
+                        .borders(Borders::ALL)
+                        .style(theme.style(Role::Accent))
+                        .title(format!("{} (Enter to pick, Esc to cancel)", picker.title))
+                        .border_type(BorderType::Plain),
+                )
+                .highlight_style(theme.highlight());
+            rect.render_widget(Clear, area);
+            rect.render_stateful_widget(list, area, &mut picker.list_state.clone());
+        }
     })?;
     Ok(())
 }
 
-#[allow(clippy::too_many_arguments)]
-pub(crate) fn word_input<T: DataRow, B: Backend>(
+pub fn word_input<T: Stored + Aged, B: Backend>(
     rx: &Receiver<Event<event::KeyEvent>>,
     new_name: &mut String,
-    loaded_data: &mut [T],
     selected: WhichRow,
     terminal: &mut Terminal<B>,
-    menu_titles: &[&str],
-    active_menu_item: MenuItem,
-    data_list_state: &mut ListState,
+    keymap: &Keymap,
+    state: &mut AppState<T>,
     change_loaded: fn(&mut [T], WhichRow, &str),
 ) -> Result<(), Box<dyn std::error::Error>> {
     loop {
-        match rx.recv()? {
+        match next_event(rx)? {
             Event::Input(evt) => match evt.code {
                 KeyCode::Backspace => {
                     let _ = new_name.pop();
                 }
-                KeyCode::Char(c) => {
-                    if c.is_alphanumeric() {
-                        new_name.push(c);
-                    } else {
-                        break;
-                    }
+                KeyCode::Char(c) if c.is_alphanumeric() => {
+                    new_name.push(c);
                 }
                 _ => {
                     break;
                 }
             },
-            Event::Tick => {
-                change_loaded(loaded_data, selected, new_name);
-                render(
-                    terminal,
-                    menu_titles,
-                    active_menu_item,
-                    data_list_state,
-                    loaded_data,
-                )?;
-            }
+            Event::Paste(text) => new_name.extend(text.chars().filter(|c| c.is_alphanumeric())),
+            Event::Resize(..) | Event::Tick => {}
+            _ => continue,
         }
+        change_loaded(&mut state.data, selected, new_name);
+        render(terminal, keymap, state)?;
     }
     Ok(())
 }
 
-pub(crate) fn render_home<'a, T: DataRow>() -> Paragraph<'a> {
+/// The next event for a popup, or why the terminal can't be read anymore.
+fn next_event(
+    rx: &Receiver<Event<KeyEvent>>,
+) -> Result<Event<KeyEvent>, Box<dyn std::error::Error>> {
+    match rx.recv()? {
+        Event::Failed(e) => Err(e.into()),
+        event => Ok(event),
+    }
+}
+
+/// Whether the screen has to be drawn again after `event`. A changed data file only changes
+/// the screen once it has been read, which popups leave until they are closed.
+pub fn redraws<I>(event: &Event<I>) -> bool {
+    match event {
+        Event::Input(_) | Event::Mouse(_) | Event::Paste(_) => true,
+        Event::Resize(..) | Event::Tick => true,
+        Event::App(_) | Event::Failed(_) => false,
+    }
+}
+
+/// Asks for a line of text in a popup over the current screen.
+/// Returns `None` if the user backs out with Esc.
+pub fn prompt_input<T: Stored + Aged, B: Backend>(
+    rx: &Receiver<Event<event::KeyEvent>>,
+    terminal: &mut Terminal<B>,
+    keymap: &Keymap,
+    state: &mut AppState<T>,
+    title: &str,
+    initial: &str,
+) -> Result<Option<String>, Box<dyn std::error::Error>> {
+    state.prompt = Some(Prompt {
+        title: title.to_owned(),
+        text: initial.to_owned(),
+    });
+    let mut dirty = true;
+    let entered = loop {
+        if dirty {
+            render(terminal, keymap, state)?;
+        }
+        let prompt = state.prompt.as_mut().expect("prompt is set");
+        let event = next_event(rx)?;
+        dirty = redraws(&event);
+        match event {
+            Event::Input(evt) => match evt.code {
+                KeyCode::Enter => break Some(prompt.text.clone()),
+                KeyCode::Esc => break None,
+                KeyCode::Backspace => {
+                    let _ = prompt.text.pop();
+                }
+                KeyCode::Char(c) => prompt.text.push(c),
+                _ => {}
+            },
+            Event::Paste(text) => prompt.text.extend(text.chars().filter(|c| !c.is_control())),
+            _ => {}
+        }
+    };
+    state.prompt = None;
+    Ok(entered)
+}
+
+/// Lets the user choose one of `options` in a popup over the current screen, starting out on
+/// `selected`. Returns the index of the chosen one, or `None` if the user backs out with Esc.
+pub fn pick_from<T: Stored + Aged, B: Backend>(
+    rx: &Receiver<Event<event::KeyEvent>>,
+    terminal: &mut Terminal<B>,
+    keymap: &Keymap,
+    state: &mut AppState<T>,
+    title: &str,
+    options: Vec<String>,
+    selected: WhichRow,
+) -> Result<Option<WhichRow>, Box<dyn std::error::Error>> {
+    let mut list_state = ListState::default();
+    list_state.select(Some(selected));
+    clamp_selection(&mut list_state, options.len());
+    state.picker = Some(Picker {
+        title: title.to_owned(),
+        options,
+        list_state,
+    });
+    let mut dirty = true;
+    let picked = loop {
+        if dirty {
+            render(terminal, keymap, state)?;
+        }
+        let picker = state.picker.as_mut().expect("picker is set");
+        let len = picker.options.len();
+        let event = next_event(rx)?;
+        dirty = redraws(&event);
+        if let Event::Input(evt) = event {
+            match evt.code {
+                KeyCode::Enter => break picker.list_state.selected(),
+                KeyCode::Esc => break None,
+                KeyCode::Down => select_next(&mut picker.list_state, len),
+                KeyCode::Up => select_previous(&mut picker.list_state, len),
+                _ => {}
+            }
+        }
+    };
+    state.picker = None;
+    Ok(picked)
+}
+
+/// Lets the user pick a date and time in a popup over the current screen, starting out on
+/// `initial`. Returns `None` if the user backs out with Esc.
+pub fn pick_datetime<T: Stored + Aged, B: Backend>(
+    rx: &Receiver<Event<event::KeyEvent>>,
+    terminal: &mut Terminal<B>,
+    keymap: &Keymap,
+    state: &mut AppState<T>,
+    title: &str,
+    initial: DateTime<Utc>,
+) -> Result<Option<DateTime<Utc>>, Box<dyn std::error::Error>> {
+    state.date_picker = Some(DatePicker::new(title, initial));
+    let mut dirty = true;
+    let picked = loop {
+        if dirty {
+            render(terminal, keymap, state)?;
+        }
+        let date_picker = state.date_picker.as_mut().expect("date picker is set");
+        let event = next_event(rx)?;
+        dirty = redraws(&event);
+        match event {
+            Event::Input(evt) => match date_picker.handle(evt.code) {
+                Picked::Editing => {}
+                Picked::Done(at) => break Some(at),
+                Picked::Cancelled => break None,
+            },
+            Event::Paste(text) => {
+                for c in text.chars().filter(|c| !c.is_control()) {
+                    date_picker.handle(KeyCode::Char(c));
+                }
+            }
+            _ => {}
+        }
+    };
+    state.date_picker = None;
+    Ok(picked)
+}
+
+/// Lets the user search every action in a popup over the current screen and returns the one
+/// picked, or `None` if the user backs out with Esc.
+pub fn pick_action<T: Stored + Aged, B: Backend>(
+    rx: &Receiver<Event<event::KeyEvent>>,
+    terminal: &mut Terminal<B>,
+    keymap: &Keymap,
+    state: &mut AppState<T>,
+) -> Result<Option<Action>, Box<dyn std::error::Error>> {
+    let row = T::row_name().to_lowercase();
+    let entries = Action::ALL
+        .iter()
+        .filter(|&&action| action != Action::Palette)
+        .filter(|&&action| action != Action::Owner || T::entity() == Entity::Pets)
+        .map(|&action| {
+            let keys: Vec<String> = keymap.keys(action).iter().map(|k| k.to_string()).collect();
+            Entry {
+                action,
+                description: action.describe(&row),
+                keys: keys.join(" "),
+            }
+        })
+        .collect();
+    state.palette = Some(Palette::new(entries));
+    let mut dirty = true;
+    let chosen = loop {
+        if dirty {
+            render(terminal, keymap, state)?;
+        }
+        let palette = state.palette.as_mut().expect("palette is open");
+        let event = next_event(rx)?;
+        dirty = redraws(&event);
+        match event {
+            Event::Input(evt) => match palette.handle(&evt) {
+                Chosen::Searching => {}
+                Chosen::Run(action) => break Some(action),
+                Chosen::Cancelled => break None,
+            },
+            Event::Paste(text) => palette.paste(&text),
+            _ => {}
+        }
+    };
+    state.palette = None;
+    Ok(chosen)
+}
+
+/// The smallest terminal the screens fit in.
+const MIN_WIDTH: u16 = 40;
+const MIN_HEIGHT: u16 = 12;
+
+/// Below this width a list and its detail pane go one above the other instead of side by side.
+const STACK_BELOW_WIDTH: u16 = 60;
+const MIN_LIST_WIDTH: u16 = 12;
+const MAX_LIST_WIDTH: u16 = 120;
+const MIN_DETAIL_WIDTH: u16 = 30;
+
+/// Where a list and the detail pane for its selected entry go on a screen. Without room for
+/// the detail pane it is left out.
+pub struct Panes {
+    pub list: Rect,
+    pub detail: Option<Rect>,
+    /// Whether the list is above the detail pane rather than to the left of it.
+    pub stacked: bool,
+}
+
+impl Panes {
+    /// Side by side, with the list `width` columns wide as far as the detail pane leaves room
+    /// for it, or stacked in narrow terminals, with the list as tall as its `rows` need.
+    pub fn new(area: Rect, width: u16, rows: usize) -> Self {
+        if area.width < STACK_BELOW_WIDTH {
+            let wanted = u16::try_from(rows + 2).unwrap_or(u16::MAX);
+            let height = wanted.min(area.height * 2 / 3).max(3).min(area.height);
+            let rest = area.height - height;
+            let detail = Rect::new(area.x, area.y + height, area.width, rest);
+            return Panes {
+                list: Rect { height, ..area },
+                detail: Some(detail).filter(|_| rest >= 3),
+                stacked: true,
+            };
+        }
+        let width = width.max(MIN_LIST_WIDTH).min(area.width - MIN_DETAIL_WIDTH);
+        Panes {
+            list: Rect { width, ..area },
+            detail: Some(Rect::new(
+                area.x + width,
+                area.y,
+                area.width - width,
+                area.height,
+            )),
+            stacked: false,
+        }
+    }
+}
+
+/// How wide a list of `entries` is: the longest of them and the borders on either side.
+fn list_width<I: Iterator<Item = String>>(entries: I) -> u16 {
+    let longest = entries
+        .map(|entry| entry.chars().count())
+        .max()
+        .unwrap_or(0);
+    u16::try_from(longest + 2).unwrap_or(u16::MAX)
+}
+
+/// The list width `<` and `>` start from: what it is set to, or else what it was drawn as.
+pub fn resized_list_width(current: Option<u16>, drawn: Option<u16>, wider: bool) -> u16 {
+    let width = current.or(drawn).unwrap_or(MIN_LIST_WIDTH);
+    let width = if wider {
+        width.saturating_add(2)
+    } else {
+        width.saturating_sub(2)
+    };
+    width.clamp(MIN_LIST_WIDTH, MAX_LIST_WIDTH)
+}
+
+/// `text` cut down to `width` characters, with an ellipsis where it was cut.
+fn ellipsize(text: &str, width: u16) -> String {
+    let width = usize::from(width);
+    match text.chars().count() {
+        count if count <= width => text.to_owned(),
+        _ => {
+            let mut cut: String = text.chars().take(width.saturating_sub(1)).collect();
+            if width > 0 {
+                cut.push('…');
+            }
+            cut
+        }
+    }
+}
+
+/// A `width` percent wide, `height` rows tall area in the middle of `area`.
+pub fn centered_rect(width: u16, height: u16, area: Rect) -> Rect {
+    // A percentage of the width is at most the width, so it fits where the product might not.
+    let width = u32::from(area.width) * u32::from(width.min(100)) / 100;
+    let width = u16::try_from(width).unwrap_or(area.width);
+    let height = height.min(area.height);
+    Rect::new(
+        area.x + (area.width - width) / 2,
+        area.y + (area.height - height) / 2,
+        width,
+        height,
+    )
+}
+
+/// The action that opens `screen`; its tab is the one highlighted while the screen is shown.
+fn screen_action<T: Stored>(screen: MenuItem) -> Action {
+    match screen {
+        MenuItem::Home => Action::Home,
+        MenuItem::Data => match T::entity() {
+            Entity::Pets => Action::Pets,
+            Entity::Children => Action::Children,
+        },
+        MenuItem::Trash => Action::Trash,
+        MenuItem::History => Action::History,
+        MenuItem::Categories => Action::Categories,
+        MenuItem::Backups => Action::Backups,
+        MenuItem::Upcoming => Action::Upcoming,
+        MenuItem::Calendar => Action::Calendar,
+        MenuItem::Stats => Action::Stats,
+    }
+}
+
+/// The tabs of the menu: the screens, then what can be done to the selected row.
+fn menu_tabs<T: Stored>() -> Vec<(Action, &'static str)> {
+    let mut tabs = vec![
+        (Action::Home, "Home"),
+        (screen_action::<T>(MenuItem::Data), T::title()),
+        (Action::Trash, "Trash"),
+        (Action::History, "History"),
+        (Action::Categories, "Categories"),
+        (Action::Backups, "Backups"),
+        (Action::Upcoming, "Upcoming"),
+        (Action::Calendar, "Month"),
+        (Action::Stats, "Stats"),
+        (Action::Add, "Add"),
+        (Action::Edit, "Edit Name"),
+        (Action::Date, "Date"),
+    ];
+    if T::entity() == Entity::Pets {
+        tabs.push((Action::Owner, "Owner"));
+    }
+    tabs.extend(vec![(Action::Delete, "Delete"), (Action::Quit, "Quit")]);
+    tabs
+}
+
+/// A menu tab with the first key of `action` underlined in `title`, or shown after the title if
+/// it isn't a letter of it.
+fn menu_tab<'a>(keymap: &Keymap, action: Action, title: &'a str, theme: &Theme) -> Spans<'a> {
+    let key_style = theme.style(Role::Accent).add_modifier(Modifier::UNDERLINED);
+    let title_style = theme.style(Role::Text);
+    let key = match keymap.keys(action).first() {
+        Some(key) => key,
+        None => return Spans::from(Span::styled(title, title_style)),
+    };
+    let in_title = key.plain_char().and_then(|c| {
+        title
+            .find(c)
+            .or_else(|| title.find(|t: char| t.eq_ignore_ascii_case(&c)))
+    });
+    match in_title {
+        Some(at) => {
+            let end = at + title[at..].chars().next().map_or(0, char::len_utf8);
+            Spans::from(vec![
+                Span::styled(&title[..at], title_style),
+                Span::styled(&title[at..end], key_style),
+                Span::styled(&title[end..], title_style),
+            ])
+        }
+        None => Spans::from(vec![
+            Span::styled(title, title_style),
+            Span::raw(" "),
+            Span::styled(key.to_string(), key_style),
+        ]),
+    }
+}
+
+/// The help on the Home screen: every action with a key, two to a line.
+fn help_lines<'a, T: Stored>(keymap: &Keymap, theme: &Theme) -> Vec<Spans<'a>> {
+    let row = T::row_name().to_lowercase();
+    let entries: Vec<(String, String)> = Action::ALL
+        .iter()
+        .filter(|&&action| action != Action::Owner || T::entity() == Entity::Pets)
+        .filter(|&&action| !keymap.keys(action).is_empty())
+        .map(|&action| {
+            let keys: Vec<String> = keymap.keys(action).iter().map(|k| k.to_string()).collect();
+            (keys.join(" "), action.describe(&row))
+        })
+        .collect();
+    entries
+        .chunks(2)
+        .map(|pair| {
+            let mut spans = Vec::new();
+            for (keys, description) in pair {
+                spans.push(Span::styled(
+                    format!("{:>12} ", keys),
+                    theme.style(Role::Accent),
+                ));
+                spans.push(Span::raw(format!("{:<40}", description)));
+            }
+            Spans::from(spans)
+        })
+        .collect()
+}
+
+pub fn render_home<'a, T: Stored>(
+    keymap: &Keymap,
+    navigation: Navigation,
+    theme: &Theme,
+) -> Paragraph<'a> {
     let mut welcome_part = vec![
         Spans::from(vec![Span::raw("")]),
-        Spans::from(vec![Span::raw("Welcome")]),
-        Spans::from(vec![Span::raw("")]),
-        Spans::from(vec![Span::raw("to")]),
-        Spans::from(vec![Span::raw("")]),
-        Spans::from(vec![Span::styled(
-            T::cli_name(),
-            Style::default().fg(Color::LightBlue),
-        )]),
+        Spans::from(vec![Span::raw("Welcome to")]),
+        Spans::from(vec![Span::styled(T::cli_name(), theme.style(Role::Title))]),
         Spans::from(vec![Span::raw("")]),
     ];
-    welcome_part.extend(T::help_part());
+    welcome_part.extend(help_lines::<T>(keymap, theme));
+    if navigation == Navigation::Modal {
+        welcome_part.push(Spans::from(vec![Span::raw(
+            "j k move, gg G top and bottom, 5j moves 5, dd deletes, V selects lines,",
+        )]));
+        welcome_part.push(Spans::from(vec![Span::raw(
+            "/ searches (n N for more), :sort age sorts, :w file.csv exports, :q quits",
+        )]));
+    }
     let home = Paragraph::new(welcome_part)
         .alignment(Alignment::Center)
         .block(
             Block::default()
                 .borders(Borders::ALL)
-                .style(Style::default().fg(Color::White))
+                .style(theme.style(Role::Text))
                 .title("Home")
                 .border_type(BorderType::Plain),
         );
     home
 }
 
-pub(crate) fn render_data<'a, T: DataRow>(
-    data_list_state: &ListState,
+/// The list of `data_list`, of which only the `rows` in view are made into items: the rest
+/// would be cut off anyway, and there may be a great many of them.
+pub fn render_data<'a, T: DataRow>(
+    title: &'a str,
     data_list: &'a [T],
-) -> (List<'a>, Table<'a>) {
+    rows: Range<WhichRow>,
+    marked: Option<(WhichRow, WhichRow)>,
+    theme: &Theme,
+    width: u16,
+) -> List<'a> {
+    let width = width.saturating_sub(2);
     let data = Block::default()
         .borders(Borders::ALL)
-        .style(Style::default().fg(Color::White))
-        .title(T::title())
+        .style(theme.style(Role::Text))
+        .title(ellipsize(title, width))
         .border_type(BorderType::Plain);
 
-    let items: Vec<_> = data_list
+    let end = rows.end.min(data_list.len());
+    let start = rows.start.min(end);
+    let items: Vec<_> = data_list[start..end]
         .iter()
-        .map(|cur_data| {
+        .zip(start..)
+        .map(|(cur_data, idx)| {
+            let style = match marked {
+                Some((first, last)) if (first..=last).contains(&idx) => theme.marked(),
+                _ => Style::default(),
+            };
             ListItem::new(Spans::from(vec![Span::styled(
-                cur_data.name().clone(),
-                Style::default(),
+                ellipsize(&cur_data.name(), width),
+                style,
             )]))
         })
         .collect();
-    #[allow(clippy::expect_fun_call)]
-    let selected_datum = data_list
-        .get(
-            data_list_state
-                .selected()
-                .expect(("there is always a selected".to_owned() + T::row_name()).as_str()),
+    List::new(items)
+        .block(data)
+        .highlight_style(theme.highlight())
+}
+
+/// Draws `list`, made of the entries from `top` on, with `selected` counted from the start of
+/// all of them.
+fn draw_window<B: Backend>(
+    frame: &mut Frame<B>,
+    list: List,
+    area: Rect,
+    selected: Option<WhichRow>,
+    top: WhichRow,
+) {
+    let mut window = ListState::default();
+    window.select(selected.map(|idx| idx.saturating_sub(top)));
+    frame.render_stateful_widget(list, area, &mut window);
+}
+
+/// Shows on the right border of a list in `area` which part of its `len` entries is in view,
+/// starting at `top`. Lists that fit go without.
+fn draw_scrollbar<B: Backend>(
+    frame: &mut Frame<B>,
+    area: Rect,
+    len: usize,
+    top: usize,
+    theme: &Theme,
+) {
+    let inner = Block::default().borders(Borders::ALL).inner(area);
+    let height = usize::from(inner.height);
+    if len <= height || height == 0 {
+        return;
+    }
+    let thumb = (height * height / len).max(1);
+    let last_top = len - height;
+    let thumb_top = (top.min(last_top) * (height - thumb) + last_top / 2) / last_top;
+    let bar: Vec<Spans> = (0..height)
+        .map(|row| {
+            if (thumb_top..thumb_top + thumb).contains(&row) {
+                Spans::from(Span::styled("█", theme.style(Role::Accent)))
+            } else {
+                Spans::from(Span::styled("│", theme.style(Role::Muted)))
+            }
+        })
+        .collect();
+    let column = Rect::new(area.right() - 1, inner.top(), 1, inner.height);
+    frame.render_widget(Paragraph::new(bar), column);
+}
+
+/// Draws the fields of a row in `area`: side by side, a column each, if they all fit, or else
+/// one to a line.
+fn draw_detail<B: Backend>(
+    frame: &mut Frame<B>,
+    area: Rect,
+    fields: &[(&'static str, String)],
+    block: Block,
+    theme: &Theme,
+) {
+    let width = block.inner(area).width;
+    let lengths: Vec<usize> = fields
+        .iter()
+        .map(|(label, value)| label.chars().count().max(value.chars().count()))
+        .collect();
+    let spacing = lengths.len().saturating_sub(1);
+    let bold = Style::default().add_modifier(Modifier::BOLD);
+    if lengths.iter().sum::<usize>() + spacing <= usize::from(width) {
+        let widths: Vec<Constraint> = lengths
+            .into_iter()
+            .map(|length| Constraint::Length(u16::try_from(length).unwrap_or(width)))
+            .collect();
+        let header = fields
+            .iter()
+            .map(|(label, _)| Cell::from(Span::styled(*label, bold)));
+        let values = fields.iter().map(|(_, value)| Cell::from(value.clone()));
+        let table = Table::new(vec![Row::new(values)])
+            .header(Row::new(header))
+            .block(block)
+            .widths(&widths);
+        frame.render_widget(table, area);
+    } else {
+        let label_width = fields.iter().map(|(label, _)| label.chars().count()).max();
+        let label_width = u16::try_from(label_width.unwrap_or(0)).unwrap_or(u16::MAX);
+        let value_width = width.saturating_sub(label_width.saturating_add(1));
+        let widths = [Constraint::Length(label_width), Constraint::Min(1)];
+        let rows = fields.iter().map(|(label, value)| {
+            Row::new(vec![
+                Cell::from(Span::styled(*label, bold)),
+                Cell::from(Span::styled(
+                    ellipsize(value, value_width),
+                    theme.style(Role::Text),
+                )),
+            ])
+        });
+        let table = Table::new(rows).block(block).widths(&widths);
+        frame.render_widget(table, area);
+    }
+}
+
+fn backup_label(backup: &Backup) -> String {
+    match backup.kind {
+        BackupKind::Rolling => backup.label.clone(),
+        BackupKind::Daily => backup.label.clone() + " (daily)",
+    }
+}
+
+pub fn render_backups<'a>(
+    backups_view: &'a BackupsView,
+    theme: &Theme,
+) -> (List<'a>, Paragraph<'a>) {
+    let items: Vec<_> = backups_view
+        .backups
+        .iter()
+        .map(|backup| ListItem::new(Spans::from(vec![Span::raw(backup_label(backup))])))
+        .collect();
+    let list = List::new(items)
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .style(theme.style(Role::Text))
+                .title("Backups")
+                .border_type(BorderType::Plain),
         )
-        .expect("exists");
+        .highlight_style(theme.highlight());
 
-    let list = List::new(items).block(data).highlight_style(
-        Style::default()
-            .bg(Color::Yellow)
-            .fg(Color::Black)
-            .add_modifier(Modifier::BOLD),
+    let (title, lines) = match backups_view.selected() {
+        None => (
+            "Changes".to_owned(),
+            vec![Spans::from(vec![Span::raw(
+                "No backups yet. One is taken before every delete, rename and restore.",
+            )])],
+        ),
+        Some(backup) if backups_view.diff.is_empty() => (
+            format!("Changes since {}", backup.label),
+            vec![Spans::from(vec![Span::raw(
+                "Identical to the current data.",
+            )])],
+        ),
+        Some(backup) => (
+            format!("Changes since {} ('r' to restore it)", backup.label),
+            backups_view
+                .diff
+                .iter()
+                .map(|line| {
+                    let role = match line.chars().next() {
+                        Some('+') => Role::Added,
+                        Some('-') => Role::Removed,
+                        _ => Role::Accent,
+                    };
+                    Spans::from(vec![Span::styled(line.clone(), theme.style(role))])
+                })
+                .collect(),
+        ),
+    };
+    let diff = Paragraph::new(lines).block(
+        Block::default()
+            .borders(Borders::ALL)
+            .style(theme.style(Role::Text))
+            .title(title)
+            .border_type(BorderType::Plain),
     );
+    (list, diff)
+}
+
+pub fn render_history<'a>(title: &'a str, history: &'a [AuditEntry], theme: &Theme) -> List<'a> {
+    let items: Vec<_> = if history.is_empty() {
+        vec![ListItem::new(Spans::from(vec![Span::raw(
+            "No changes logged yet.",
+        )]))]
+    } else {
+        history
+            .iter()
+            .map(|entry| {
+                ListItem::new(Spans::from(vec![
+                    Span::styled(
+                        entry.at.format("%Y-%m-%d %H:%M:%S ").to_string(),
+                        theme.style(Role::Info),
+                    ),
+                    Span::styled(format!("{:<10} ", entry.user), theme.style(Role::Accent)),
+                    Span::raw(entry.describe()),
+                ]))
+            })
+            .collect()
+    };
+    List::new(items)
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .style(theme.style(Role::Text))
+                .title(title)
+                .border_type(BorderType::Plain),
+        )
+        .highlight_style(theme.highlight())
+}
+
+pub fn render_categories<'a>(
+    categories_view: &'a CategoriesView,
+    theme: &Theme,
+) -> (List<'a>, Paragraph<'a>) {
+    let items: Vec<_> = categories_view
+        .categories
+        .iter()
+        .map(|(category, count)| {
+            ListItem::new(Spans::from(vec![
+                Span::raw(category.to_string()),
+                Span::styled(format!(" ({})", count), theme.style(Role::Info)),
+            ]))
+        })
+        .collect();
+    let (title, highlight) = match &categories_view.merging {
+        Some(from) => (format!("Merge '{}' into", from), Role::Merging),
+        None => ("Categories".to_owned(), Role::Selection),
+    };
+    let list = List::new(items)
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .style(theme.style(Role::Text))
+                .title(title)
+                .border_type(BorderType::Plain),
+        )
+        .highlight_style(theme.highlight_on(highlight));
+
+    let help = match &categories_view.merging {
+        Some(from) => vec![
+            Spans::from(vec![Span::raw(format!(
+                "Pick the category the pets in '{}' should move to",
+                from
+            ))]),
+            Spans::from(vec![Span::raw("and press Enter. Esc cancels the merge.")]),
+        ],
+        None => vec![
+            Spans::from(vec![Span::raw("'a' adds a category")]),
+            Spans::from(vec![Span::raw(
+                "'e' renames the selected one, its pets move along",
+            )]),
+            Spans::from(vec![Span::raw("'m' merges the selected one into another")]),
+            Spans::from(vec![Span::raw(
+                "'d' deletes the selected one, if no pet is in it",
+            )]),
+        ],
+    };
+    let help = Paragraph::new(help).block(
+        Block::default()
+            .borders(Borders::ALL)
+            .style(theme.style(Role::Text))
+            .title("Manage")
+            .border_type(BorderType::Plain),
+    );
+    (list, help)
+}
+
+pub fn render_upcoming<'a>(
+    reminders: &[Reminder],
+    today: NaiveDate,
+    days: u32,
+    theme: &Theme,
+) -> Table<'a> {
+    let rows: Vec<_> = reminders
+        .iter()
+        .map(|reminder| {
+            Row::new(vec![
+                Cell::from(Span::raw(reminder.date.format("%a %Y-%m-%d").to_string())),
+                Cell::from(Span::raw(match (reminder.date - today).num_days() {
+                    0 => "today".to_owned(),
+                    1 => "tomorrow".to_owned(),
+                    n => format!("in {} days", n),
+                })),
+                Cell::from(Span::raw(reminder.entity.to_string())),
+                Cell::from(Span::raw(reminder.to_string())),
+            ])
+        })
+        .collect();
+    let bold = Style::default().add_modifier(Modifier::BOLD);
+    Table::new(rows)
+        .header(Row::new(vec![
+            Cell::from(Span::styled("Date", bold)),
+            Cell::from(Span::styled("When", bold)),
+            Cell::from(Span::styled("Kind", bold)),
+            Cell::from(Span::styled("What", bold)),
+        ]))
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .style(theme.style(Role::Text))
+                .title(format!(
+                    "Birthdays and adoption anniversaries in the next {} days",
+                    days
+                ))
+                .border_type(BorderType::Plain),
+        )
+        .widths(&[
+            Constraint::Percentage(20),
+            Constraint::Percentage(15),
+            Constraint::Percentage(10),
+            Constraint::Percentage(55),
+        ])
+}
+
+pub fn render_calendar<'a, T: Stored>(
+    calendar: &CalendarView,
+    rows: &'a [T],
+    theme: &Theme,
+) -> (Table<'a>, List<'a>) {
+    let today = Utc::now().date_naive();
+    let first = calendar
+        .day
+        .with_day(1)
+        .expect("every month has a first day");
+    let mut day = first - chrono::Duration::days(first.weekday().num_days_from_monday().into());
+    let mut weeks = Vec::new();
+    while day.month() == first.month() || day < first {
+        let mut cells = Vec::new();
+        for _ in 0..7 {
+            let count = calendar.per_day.get(&day).map_or(0, Vec::len);
+            let text = match count {
+                0 => format!("{:>2}", day.day()),
+                n => format!("{:>2} ({})", day.day(), n),
+            };
+            let mut style = match (day.month() == first.month(), count) {
+                (false, _) => theme.style(Role::Muted),
+                (true, 0) => theme.style(Role::Text),
+                (true, _) => theme.style(Role::Info).add_modifier(Modifier::BOLD),
+            };
+            if day == today {
+                style = style.add_modifier(Modifier::UNDERLINED);
+            }
+            if day == calendar.day {
+                style = style.patch(theme.highlight());
+            }
+            cells.push(Cell::from(Span::styled(text, style)));
+            day = day
+                .succ_opt()
+                .expect("the calendar stays within chrono's range");
+        }
+        weeks.push(Row::new(cells).height(2));
+    }
+    let bold = Style::default().add_modifier(Modifier::BOLD);
+    let grid = Table::new(weeks)
+        .header(Row::new(
+            ["Mon", "Tue", "Wed", "Thu", "Fri", "Sat", "Sun"]
+                .iter()
+                .map(|name| Cell::from(Span::styled(*name, bold))),
+        ))
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .style(theme.style(Role::Text))
+                .title(format!(
+                    "{} by {} (arrows move, PageUp/PageDown change month, 'f' changes field, 'n'/'N' pick, Enter shows)",
+                    calendar.day.format("%B %Y"),
+                    calendar.field
+                ))
+                .border_type(BorderType::Plain),
+        )
+        .widths(&[Constraint::Ratio(1, 7); 7]);
 
-    let data_detail = selected_datum.to_table();
+    let items: Vec<_> = calendar
+        .rows_of_day()
+        .iter()
+        .filter_map(|&idx| rows.get(idx))
+        .map(|row| {
+            ListItem::new(Spans::from(vec![Span::raw(format!(
+                "{} ({})",
+                row.name(),
+                row.id()
+            ))]))
+        })
+        .collect();
+    let day_list = List::new(items)
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .style(theme.style(Role::Text))
+                .title(calendar.day.format("%a %Y-%m-%d").to_string())
+                .border_type(BorderType::Plain),
+        )
+        .highlight_style(theme.highlight());
+    (grid, day_list)
+}
 
-    (list, data_detail)
+pub fn render_group_chart<'a, T: DataRow>(
+    per_group: &'a [(&'a str, u64)],
+    theme: &Theme,
+) -> BarChart<'a> {
+    let title = match per_group.is_empty() {
+        true => format!("{} don't come in categories", T::title()),
+        false => format!("{} per category", T::title()),
+    };
+    BarChart::default()
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .style(theme.style(Role::Text))
+                .title(title)
+                .border_type(BorderType::Plain),
+        )
+        .data(per_group)
+        .bar_width(9)
+        .bar_gap(2)
+        .bar_style(theme.style(Role::Accent))
+        .value_style(theme.highlight())
+}
+
+/// The points of the line through `per_age`, which drops to 0 for the ages it leaves out.
+fn age_points(per_age: &[(u32, u64)]) -> Vec<(f64, f64)> {
+    let mut points = Vec::new();
+    let mut next = 0;
+    for &(age, count) in per_age {
+        if age > next {
+            points.push((f64::from(next), 0.0));
+            if age - 1 > next {
+                points.push((f64::from(age - 1), 0.0));
+            }
+        }
+        points.push((f64::from(age), count as f64));
+        next = age.saturating_add(1);
+    }
+    points
+}
+
+pub fn render_age_chart<'a, T: DataRow>(
+    stats: &Stats,
+    unit: AgeUnit,
+    dataset: &'a [Dataset<'a>],
+    theme: &Theme,
+) -> Chart<'a> {
+    let oldest = stats.per_age.last().map_or(0, |&(age, _)| age).max(1) as f64;
+    let most = stats
+        .per_age
+        .iter()
+        .map(|&(_, count)| count)
+        .max()
+        .unwrap_or(0)
+        .max(1) as f64;
+    let average = match stats.average_age {
+        Some(average) => format!("average {:.1}", average),
+        None => "no ages".to_owned(),
+    };
+    let label = |value: f64| Span::raw(format!("{}", value));
+    Chart::new(dataset.to_vec())
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .style(theme.style(Role::Text))
+                .title(format!("{} per age in {}, {}", T::title(), unit, average))
+                .border_type(BorderType::Plain),
+        )
+        .x_axis(
+            Axis::default()
+                .title("age")
+                .bounds([0.0, oldest])
+                .labels(vec![
+                    label(0.0),
+                    label((oldest / 2.0).round()),
+                    label(oldest),
+                ]),
+        )
+        .y_axis(
+            Axis::default()
+                .title("count")
+                .bounds([0.0, most])
+                .labels(vec![label(0.0), label(most)]),
+        )
+}
+
+pub fn render_month_sparkline<'a, T: DataRow>(stats: &'a Stats, theme: &Theme) -> Sparkline<'a> {
+    Sparkline::default()
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .style(theme.style(Role::Text))
+                .title(format!(
+                    "{} {} per month by {}, over the last {} months (most recent on the right)",
+                    stats.count,
+                    T::title().to_lowercase(),
+                    T::calendar_field(),
+                    stats::MONTHS
+                ))
+                .border_type(BorderType::Plain),
+        )
+        .data(&stats.per_month)
+        .style(theme.style(Role::Info))
 }
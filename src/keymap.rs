@@ -0,0 +1,489 @@
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::str::FromStr;
+
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+
+use crate::db_stuff::Error;
+
+/// Everything a key can do in the TUI. What an action does can depend on the screen: `delete`
+/// trashes the selected row on the data screen, purges it in the trash and drops a category on
+/// the Categories screen.
+#[derive(Serialize, Deserialize, Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[serde(rename_all = "kebab-case")]
+pub enum Action {
+    Quit,
+    Home,
+    Pets,
+    Children,
+    Trash,
+    History,
+    Categories,
+    Backups,
+    Upcoming,
+    Calendar,
+    Stats,
+    Add,
+    Edit,
+    Date,
+    Owner,
+    Delete,
+    Restore,
+    Merge,
+    Sort,
+    Export,
+    Theme,
+    ShrinkList,
+    GrowList,
+    Search,
+    SearchNext,
+    SearchPrevious,
+    OlderVersion,
+    NewerVersion,
+    NextField,
+    Up,
+    Down,
+    Left,
+    Right,
+    PageUp,
+    PageDown,
+    Top,
+    Bottom,
+    Confirm,
+    Back,
+    Palette,
+}
+
+impl Action {
+    /// In the order the help lists them.
+    pub const ALL: &'static [Action] = &[
+        Action::Home,
+        Action::Pets,
+        Action::Children,
+        Action::Trash,
+        Action::History,
+        Action::Categories,
+        Action::Backups,
+        Action::Upcoming,
+        Action::Calendar,
+        Action::Stats,
+        Action::Add,
+        Action::Edit,
+        Action::Date,
+        Action::Owner,
+        Action::Delete,
+        Action::Restore,
+        Action::Merge,
+        Action::Sort,
+        Action::Export,
+        Action::Theme,
+        Action::ShrinkList,
+        Action::GrowList,
+        Action::Search,
+        Action::SearchNext,
+        Action::SearchPrevious,
+        Action::OlderVersion,
+        Action::NewerVersion,
+        Action::NextField,
+        Action::Up,
+        Action::Down,
+        Action::Left,
+        Action::Right,
+        Action::PageUp,
+        Action::PageDown,
+        Action::Top,
+        Action::Bottom,
+        Action::Confirm,
+        Action::Back,
+        Action::Palette,
+        Action::Quit,
+    ];
+
+    /// What the action does, for the help on the Home screen. `row` is what a row is called,
+    /// like "pet".
+    pub fn describe(self, row: &str) -> String {
+        match self {
+            Action::Quit => "quit".to_owned(),
+            Action::Home => "this screen".to_owned(),
+            Action::Pets => "the pets".to_owned(),
+            Action::Children => "the children".to_owned(),
+            Action::Trash => "the trash".to_owned(),
+            Action::History => format!("the changes to the selected {}", row),
+            Action::Categories => "the pet categories".to_owned(),
+            Action::Backups => "the backups".to_owned(),
+            Action::Upcoming => "birthdays and anniversaries coming up".to_owned(),
+            Action::Calendar => "a calendar of the month".to_owned(),
+            Action::Stats => "charts and figures".to_owned(),
+            Action::Add => format!("add a random {} or a category", row),
+            Action::Edit => format!("rename the selected {} or category", row),
+            Action::Date => format!("change a date of the selected {}", row),
+            Action::Owner => "pick the owner of the selected pet".to_owned(),
+            Action::Delete => "trash, purge or drop the selected one".to_owned(),
+            Action::Restore => "restore from the trash or a backup".to_owned(),
+            Action::Merge => "merge the selected category".to_owned(),
+            Action::Sort => format!("sort the {}s by a field", row),
+            Action::Export => format!("export the {}s to a CSV file", row),
+            Action::Theme => "switch the color theme".to_owned(),
+            Action::ShrinkList => "a narrower list, a wider detail".to_owned(),
+            Action::GrowList => "a wider list, a narrower detail".to_owned(),
+            Action::Search => format!("search the {}s by name", row),
+            Action::SearchNext => "the next match, the next row of the day".to_owned(),
+            Action::SearchPrevious => "the previous match, the previous row of the day".to_owned(),
+            Action::OlderVersion => format!("an older version of the {}", row),
+            Action::NewerVersion => format!("a newer version of the {}", row),
+            Action::NextField => "plot the calendar by another date".to_owned(),
+            Action::Up => "move up, a week back".to_owned(),
+            Action::Down => "move down, a week ahead".to_owned(),
+            Action::Left => "younger by one, a day back".to_owned(),
+            Action::Right => "older by one, a day ahead".to_owned(),
+            Action::PageUp => "a page up, a month back".to_owned(),
+            Action::PageDown => "a page down, a month ahead".to_owned(),
+            Action::Top => "to the top of the list".to_owned(),
+            Action::Bottom => "to the bottom of the list".to_owned(),
+            Action::Confirm => "open a change, finish a merge, show the row of the day".to_owned(),
+            Action::Back => "back to the live data".to_owned(),
+            Action::Palette => "every action, to search and run".to_owned(),
+        }
+    }
+}
+
+/// The name the action has in the config, like `older-version`.
+impl fmt::Display for Action {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match serde_json::to_value(self) {
+            Ok(serde_json::Value::String(name)) => f.write_str(&name),
+            _ => write!(f, "{:?}", self),
+        }
+    }
+}
+
+/// A key together with the modifiers held down with it, like `ctrl-k`. Shift is folded into
+/// the character for character keys, so `H` and `shift-h` are the same binding.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct KeyBinding {
+    code: KeyCode,
+    modifiers: KeyModifiers,
+}
+
+impl KeyBinding {
+    pub fn new(code: KeyCode, modifiers: KeyModifiers) -> Self {
+        match code {
+            KeyCode::Char(c) if modifiers.contains(KeyModifiers::SHIFT) => KeyBinding {
+                code: KeyCode::Char(c.to_ascii_uppercase()),
+                modifiers: modifiers - KeyModifiers::SHIFT,
+            },
+            code => KeyBinding { code, modifiers },
+        }
+    }
+
+    /// The character of a key pressed without modifiers.
+    pub fn plain_char(&self) -> Option<char> {
+        match self.code {
+            KeyCode::Char(c) if self.modifiers.is_empty() => Some(c),
+            _ => None,
+        }
+    }
+}
+
+impl From<KeyCode> for KeyBinding {
+    fn from(code: KeyCode) -> Self {
+        KeyBinding::new(code, KeyModifiers::NONE)
+    }
+}
+
+impl From<&KeyEvent> for KeyBinding {
+    fn from(event: &KeyEvent) -> Self {
+        KeyBinding::new(event.code, event.modifiers)
+    }
+}
+
+const NAMED_KEYS: &[(&str, KeyCode)] = &[
+    ("Enter", KeyCode::Enter),
+    ("Esc", KeyCode::Esc),
+    ("Tab", KeyCode::Tab),
+    ("BackTab", KeyCode::BackTab),
+    ("Backspace", KeyCode::Backspace),
+    ("Delete", KeyCode::Delete),
+    ("Insert", KeyCode::Insert),
+    ("Home", KeyCode::Home),
+    ("End", KeyCode::End),
+    ("PageUp", KeyCode::PageUp),
+    ("PageDown", KeyCode::PageDown),
+    ("Up", KeyCode::Up),
+    ("Down", KeyCode::Down),
+    ("Left", KeyCode::Left),
+    ("Right", KeyCode::Right),
+    ("Space", KeyCode::Char(' ')),
+];
+
+const MODIFIERS: &[(&str, KeyModifiers)] = &[
+    ("Ctrl", KeyModifiers::CONTROL),
+    ("Alt", KeyModifiers::ALT),
+    ("Shift", KeyModifiers::SHIFT),
+];
+
+impl FromStr for KeyBinding {
+    type Err = Error;
+
+    /// Reads things like `q`, `H`, `enter`, `pagedown`, `f5`, `ctrl-k` or `alt-shift-left`.
+    /// Names are case-insensitive, single characters are not.
+    fn from_str(input: &str) -> Result<Self, Error> {
+        let invalid = |why: &str| Error::Invalid(format!("'{}' is not a key: {}", input, why));
+        let (modifier_names, key) = match input.strip_suffix("--") {
+            Some(modifier_names) => (modifier_names, "-"),
+            None if input == "-" => ("", "-"),
+            None => input.rsplit_once('-').unwrap_or(("", input)),
+        };
+        let mut modifiers = KeyModifiers::NONE;
+        for name in modifier_names.split('-').filter(|name| !name.is_empty()) {
+            let (_, modifier) = MODIFIERS
+                .iter()
+                .find(|(known, _)| known.eq_ignore_ascii_case(name))
+                .ok_or_else(|| invalid("modifiers are ctrl, alt and shift"))?;
+            modifiers |= *modifier;
+        }
+        let mut chars = key.chars();
+        let code = match (chars.next(), chars.next()) {
+            (None, _) => return Err(invalid("the key is missing")),
+            (Some(c), None) => KeyCode::Char(c),
+            _ => match NAMED_KEYS
+                .iter()
+                .find(|(name, _)| name.eq_ignore_ascii_case(key))
+            {
+                Some((_, code)) => *code,
+                None => key
+                    .strip_prefix(['f', 'F'])
+                    .and_then(|n| n.parse::<u8>().ok())
+                    .filter(|n| (1..=12).contains(n))
+                    .map(KeyCode::F)
+                    .ok_or_else(|| invalid("unknown key name"))?,
+            },
+        };
+        Ok(KeyBinding::new(code, modifiers))
+    }
+}
+
+impl fmt::Display for KeyBinding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (name, modifier) in MODIFIERS {
+            if self.modifiers.contains(*modifier) {
+                write!(f, "{}-", name)?;
+            }
+        }
+        match NAMED_KEYS.iter().find(|(_, code)| *code == self.code) {
+            Some((name, _)) => f.write_str(name),
+            None => match self.code {
+                KeyCode::Char(c) => write!(f, "{}", c),
+                KeyCode::F(n) => write!(f, "F{}", n),
+                other => write!(f, "{:?}", other),
+            },
+        }
+    }
+}
+
+impl Serialize for KeyBinding {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for KeyBinding {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let input = String::deserialize(deserializer)?;
+        input.parse().map_err(de::Error::custom)
+    }
+}
+
+/// The keys of one action in the config: a single key, or a list of them.
+#[derive(Serialize, Clone, Debug)]
+pub struct Keys(Vec<KeyBinding>);
+
+impl<'de> Deserialize<'de> for Keys {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        #[derive(Deserialize)]
+        #[serde(untagged)]
+        enum OneOrMany {
+            One(String),
+            Many(Vec<String>),
+        }
+        let keys = match OneOrMany::deserialize(deserializer)
+            .map_err(|_| de::Error::custom("expected a key or a list of keys"))?
+        {
+            OneOrMany::One(key) => vec![key],
+            OneOrMany::Many(keys) => keys,
+        };
+        keys.iter()
+            .map(|key| key.parse().map_err(de::Error::custom))
+            .collect::<Result<_, _>>()
+            .map(Keys)
+    }
+}
+
+/// The `[keys]` section of the config: the actions whose keys differ from the defaults.
+pub type KeyConfig = BTreeMap<Action, Keys>;
+
+fn default_keys(action: Action) -> Vec<KeyBinding> {
+    let chars = |keys: &str| keys.chars().map(|c| KeyCode::Char(c).into()).collect();
+    match action {
+        Action::Quit => chars("q"),
+        Action::Home => chars("h"),
+        Action::Pets => chars("p"),
+        Action::Children => chars("c"),
+        Action::Trash => chars("t"),
+        Action::History => chars("H"),
+        Action::Categories => chars("C"),
+        Action::Backups => chars("b"),
+        Action::Upcoming => chars("u"),
+        Action::Calendar => chars("M"),
+        Action::Stats => chars("S"),
+        Action::Add => chars("a"),
+        Action::Edit => chars("e"),
+        Action::Date => chars("D"),
+        Action::Owner => chars("o"),
+        Action::Delete => chars("d"),
+        Action::Restore => chars("r"),
+        Action::Merge => chars("m"),
+        Action::Sort => chars("s"),
+        Action::Export => Vec::new(),
+        Action::Theme => chars("T"),
+        Action::ShrinkList => chars("<"),
+        Action::GrowList => chars(">"),
+        Action::Search => chars("/"),
+        Action::SearchNext => chars("n"),
+        Action::SearchPrevious => chars("N"),
+        Action::OlderVersion => chars("["),
+        Action::NewerVersion => chars("]"),
+        Action::NextField => chars("f"),
+        Action::Up => vec![KeyCode::Up.into()],
+        Action::Down => vec![KeyCode::Down.into()],
+        Action::Left => vec![KeyCode::Left.into()],
+        Action::Right => vec![KeyCode::Right.into()],
+        Action::PageUp => vec![KeyCode::PageUp.into()],
+        Action::PageDown => vec![KeyCode::PageDown.into()],
+        Action::Top => vec![KeyCode::Home.into()],
+        Action::Bottom => vec![KeyCode::End.into()],
+        Action::Confirm => vec![KeyCode::Enter.into()],
+        Action::Back => vec![KeyCode::Esc.into()],
+        Action::Palette => vec![KeyBinding::new(KeyCode::Char('k'), KeyModifiers::CONTROL)],
+    }
+}
+
+/// Which key does what. Built from the defaults with the `[keys]` section of the config on top.
+pub struct Keymap {
+    actions: HashMap<KeyBinding, Action>,
+    keys: BTreeMap<Action, Vec<KeyBinding>>,
+}
+
+impl Keymap {
+    /// An action listed in `config` loses its default keys. Fails if a key ends up bound to two
+    /// actions, or if nothing is left to quit with.
+    pub fn new(config: &KeyConfig) -> Result<Self, Error> {
+        let keys: BTreeMap<Action, Vec<KeyBinding>> = Action::ALL
+            .iter()
+            .map(|&action| match config.get(&action) {
+                Some(Keys(keys)) => (action, keys.clone()),
+                None => (action, default_keys(action)),
+            })
+            .collect();
+        if keys[&Action::Quit].is_empty() {
+            return Err(Error::Invalid("quit needs a key".to_owned()));
+        }
+        let mut actions = HashMap::new();
+        for &action in Action::ALL {
+            for key in &keys[&action] {
+                match actions.insert(*key, action) {
+                    Some(other) if other != action => {
+                        return Err(Error::Invalid(format!(
+                            "'{}' is bound to both {} and {}",
+                            key, other, action
+                        )));
+                    }
+                    _ => {}
+                }
+            }
+        }
+        Ok(Keymap { actions, keys })
+    }
+
+    pub fn action(&self, event: &KeyEvent) -> Option<Action> {
+        self.actions.get(&KeyBinding::from(event)).copied()
+    }
+
+    pub fn keys(&self, action: Action) -> &[KeyBinding] {
+        &self.keys[&action]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key(input: &str) -> KeyBinding {
+        input.parse().unwrap()
+    }
+
+    #[test]
+    fn parses_characters_names_and_modifiers() {
+        assert_eq!(key("q"), KeyCode::Char('q').into());
+        assert_eq!(key("H"), KeyCode::Char('H').into());
+        assert_eq!(key("shift-h"), key("H"));
+        assert_eq!(key("enter"), KeyCode::Enter.into());
+        assert_eq!(key("PageDown"), KeyCode::PageDown.into());
+        assert_eq!(key("space"), KeyCode::Char(' ').into());
+        assert_eq!(key("f5"), KeyCode::F(5).into());
+        assert_eq!(
+            key("ctrl-k"),
+            KeyBinding::new(KeyCode::Char('k'), KeyModifiers::CONTROL)
+        );
+        assert_eq!(
+            key("Alt-Shift-Left"),
+            KeyBinding::new(KeyCode::Left, KeyModifiers::ALT | KeyModifiers::SHIFT)
+        );
+        assert_eq!(key("-"), KeyCode::Char('-').into());
+        assert_eq!(
+            key("ctrl--"),
+            KeyBinding::new(KeyCode::Char('-'), KeyModifiers::CONTROL)
+        );
+    }
+
+    #[test]
+    fn refuses_what_is_not_a_key() {
+        for input in ["", "ctrl-", "f13", "f0", "nope", "hyper-x"] {
+            assert!(input.parse::<KeyBinding>().is_err(), "{}", input);
+        }
+    }
+
+    #[test]
+    fn the_default_keys_do_not_conflict() {
+        let keymap = Keymap::new(&KeyConfig::new()).unwrap();
+        assert_eq!(
+            keymap.action(&KeyEvent::from(KeyCode::Char('q'))),
+            Some(Action::Quit)
+        );
+    }
+
+    #[test]
+    fn a_rebound_action_loses_its_default_keys() {
+        let config = KeyConfig::from([(Action::Add, Keys(vec![key("+")]))]);
+        let keymap = Keymap::new(&config).unwrap();
+        assert_eq!(
+            keymap.action(&KeyEvent::from(KeyCode::Char('+'))),
+            Some(Action::Add)
+        );
+        assert_eq!(keymap.action(&KeyEvent::from(KeyCode::Char('a'))), None);
+    }
+
+    #[test]
+    fn reports_a_key_bound_to_two_actions() {
+        let config = KeyConfig::from([(Action::Add, Keys(vec![key("q")]))]);
+        let error = Keymap::new(&config).err().unwrap().to_string();
+        assert!(error.contains("bound to both"), "{}", error);
+    }
+
+    #[test]
+    fn quit_needs_a_key() {
+        let config = KeyConfig::from([(Action::Quit, Keys(Vec::new()))]);
+        assert!(Keymap::new(&config).is_err());
+    }
+}
@@ -0,0 +1,22 @@
+//! The pets and children, how they are stored and the TUI that shows them. The binary in
+//! `main.rs` puts these together; the examples use them too.
+
+pub mod audit;
+pub mod backups;
+pub mod category;
+pub mod child;
+pub mod config;
+pub mod csv_io;
+pub mod data_row;
+pub mod date_picker;
+pub mod db_stuff;
+pub mod generic_tui;
+pub mod keymap;
+pub mod migrations;
+pub mod modal;
+pub mod ownership;
+pub mod palette;
+pub mod pet;
+pub mod stats;
+pub mod theme;
+pub mod upcoming;
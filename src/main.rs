@@ -1,191 +1,846 @@
-use crossterm::event::KeyCode;
-use rand::{distributions::Alphanumeric, prelude::*};
-use std::fs;
-
-use tui::widgets::ListState;
+use chrono::prelude::*;
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError};
 
-mod data_row;
-use data_row::{Aged, DataRow};
+use crossterm::event::KeyEvent;
+use rand::{distributions::Alphanumeric, prelude::*};
+use tui::{backend::Backend, layout::Rect, Terminal};
 
-mod pet;
-use pet::Pet;
-mod child;
+mod cli;
 
-mod generic_tui;
-use generic_tui::*;
+use rust_commandline_example::audit::Logged;
+use rust_commandline_example::child::Child;
+use rust_commandline_example::config::{AgeBounds, Config};
+use rust_commandline_example::data_row::{Aged, DataRow, Field, FieldKind, SortKey};
+use rust_commandline_example::db_stuff::{Database, Entity, Error, Store, Stored};
+use rust_commandline_example::generic_tui::*;
+use rust_commandline_example::keymap::{Action, Keymap};
+use rust_commandline_example::modal::{Command, KeyHandler};
+use rust_commandline_example::ownership::OnDelete;
+use rust_commandline_example::pet::Pet;
+use rust_commandline_example::theme::{Preset, Theme};
+use rust_commandline_example::{audit, backups, csv_io};
 
 fn main() -> Result<(), Box<dyn std::error::Error>> {
-    let (rx, _join_handle) = io_handler();
+    let args = match cli::parse_args(std::env::args().skip(1)) {
+        Ok(args) => args,
+        Err(e) => {
+            eprintln!("{}", e);
+            std::process::exit(2);
+        }
+    };
+    let mut config = match Config::load(&args.config_path) {
+        Ok(config) => config,
+        Err(e) => {
+            eprintln!("error reading {}: {}", args.config_path, e);
+            std::process::exit(2);
+        }
+    };
+    config.theme.apply_no_color();
+    let keymap = match Keymap::new(&config.keys) {
+        Ok(keymap) => keymap,
+        Err(e) => {
+            eprintln!("error in the [keys] of {}: {}", args.config_path, e);
+            std::process::exit(2);
+        }
+    };
+    let store = match Store::open(&args.db_path, config.backups.clone()) {
+        Ok(store) => store,
+        Err(e) => {
+            eprintln!("error: {}", e);
+            std::process::exit(2);
+        }
+    };
+    if let Some(command) = args.command {
+        if let Err(e) = cli::run(command, &store, &config) {
+            eprintln!("error: {}", e);
+            std::process::exit(1);
+        }
+        return Ok(());
+    }
+    // Only a TUI that can change the data empties the trash, looking at the past leaves it be.
+    if args.as_of.is_none() {
+        if let Err(e) = cli::purge_expired_trash(&store, &config) {
+            eprintln!("error emptying the trash: {}", e);
+            std::process::exit(1);
+        }
+    }
 
-    let mut terminal = get_terminal()?;
+    let (tx, rx) = mpsc::channel();
+    let mut terminal = get_terminal(config.tui.mouse)?;
+    // Reading the terminal before it is in raw mode would get whole lines instead of keys.
+    let io = io_handler(tx.clone());
+    let watch = watch_file(store.path.clone(), tx);
+    let result = run_entities(&store, &mut config, &keymap, &rx, &mut terminal, args.as_of);
+    io.stop();
+    watch.stop();
+    tui_cleanup(&mut terminal)?;
+    result
+}
+
+/// Runs the TUI for pets, children and back again as the user switches, until they quit.
+fn run_entities<B: Backend>(
+    store: &Store,
+    config: &mut Config,
+    keymap: &Keymap,
+    rx: &Receiver<Event<KeyEvent>>,
+    terminal: &mut Terminal<B>,
+    as_of: Option<DateTime<Utc>>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let (mut entity, mut as_of) = (Entity::Pets, as_of);
+    let mut active_menu_item = match as_of {
+        Some(_) => MenuItem::Data,
+        None => MenuItem::Home,
+    };
+    loop {
+        let exit = match entity {
+            Entity::Pets => {
+                run_tui::<Pet, _>(store, config, keymap, rx, terminal, active_menu_item, as_of)?
+            }
+            Entity::Children => {
+                run_tui::<Child, _>(store, config, keymap, rx, terminal, active_menu_item, as_of)?
+            }
+        };
+        match exit {
+            Exit::Quit => return Ok(()),
+            Exit::Switch { to, as_of: at } => {
+                entity = to;
+                as_of = at;
+                active_menu_item = MenuItem::Data;
+            }
+        }
+    }
+}
 
-    let menu_titles = Pet::menu_titles();
-    let mut active_menu_item = MenuItem::Home;
+/// How the TUI for one kind of row was left.
+enum Exit {
+    Quit,
+    /// Over to the screens of another kind of row, still looking at the data as of `as_of`.
+    Switch {
+        to: Entity,
+        as_of: Option<DateTime<Utc>>,
+    },
+}
 
-    let mut pet_list_state = get_data_list_state();
+/// Runs the TUI for the rows of `T` until the user quits or switches to another kind of row.
+fn run_tui<T: Stored + Aged, B: Backend>(
+    store: &Store,
+    config: &mut Config,
+    keymap: &Keymap,
+    rx: &Receiver<Event<KeyEvent>>,
+    terminal: &mut Terminal<B>,
+    active_menu_item: MenuItem,
+    as_of: Option<DateTime<Utc>>,
+) -> Result<Exit, Box<dyn std::error::Error>> {
+    let mut state = AppState::<T>::load(store)?;
+    if let Some(at) = as_of {
+        state.view_as_of(store, at)?;
+    }
+    state.active_menu_item = active_menu_item;
+    state.upcoming_days = config.upcoming.days;
+    state.age_unit = config.ages.of(T::entity()).unit;
 
-    let mut loaded_pets = read_db().expect("can fetch pet list");
+    let mut handler = KeyHandler::new(config.tui.navigation);
+    state.navigation = config.tui.navigation;
+    state.theme = Theme::new(&config.theme);
+    state.list_width = config.tui.list_width;
 
+    // Drawn again only when something may have changed since the last frame.
+    let mut dirty = true;
     loop {
-        render(
-            &mut terminal,
-            &menu_titles,
-            active_menu_item,
-            &mut pet_list_state,
-            &loaded_pets,
-        )?;
-
-        match rx.recv()? {
-            Event::Input(event) => match event.code {
-                KeyCode::Char('q') => {
-                    tui_cleanup(&mut terminal)?;
-                    break;
+        if dirty {
+            render(terminal, keymap, &mut state)?;
+        }
+
+        let event = match config.tui.tick_rate().filter(|_| state.shows_time()) {
+            Some(tick_rate) => match rx.recv_timeout(tick_rate) {
+                Ok(event) => event,
+                Err(RecvTimeoutError::Timeout) => Event::Tick,
+                Err(e) => return Err(e.into()),
+            },
+            None => rx.recv()?,
+        };
+        dirty = redraws(&event);
+        let commands = match event {
+            Event::Input(event) => {
+                state.status = None;
+                handler.handle(&event, keymap)
+            }
+            Event::Mouse(mouse) => {
+                state.status = None;
+                state.mouse(&mouse)
+            }
+            Event::Paste(text) => {
+                state.status = None;
+                handler.paste(&text);
+                Vec::new()
+            }
+            Event::App(AppEvent::DataChanged) => {
+                // A file another program is halfway through writing may not parse; the data
+                // stays as it was until it does.
+                if state.as_of.is_none() {
+                    state.apply(store.read());
+                    dirty = true;
                 }
-                KeyCode::Char('h') => active_menu_item = MenuItem::Home,
-                KeyCode::Char('p') => active_menu_item = MenuItem::Data,
-                KeyCode::Char('a') => {
-                    loaded_pets = add_random_pet_to_db().expect("can add new random pet");
+                Vec::new()
+            }
+            Event::Resize(columns, rows) => {
+                terminal.resize(Rect::new(0, 0, columns, rows))?;
+                Vec::new()
+            }
+            Event::Tick => Vec::new(),
+            Event::Failed(e) => return Err(e.into()),
+        };
+        state.command_line = handler.prompt();
+        for command in commands {
+            let actions = match prepare(command, store, &mut state)? {
+                Some(actions) => actions,
+                None => break,
+            };
+            for action in actions {
+                if let Some(exit) =
+                    perform(action, store, config, keymap, rx, terminal, &mut state)?
+                {
+                    return Ok(exit);
                 }
-                KeyCode::Char('d') => {
-                    remove_pet_at_index(&mut pet_list_state).expect("can remove pet");
-                    loaded_pets = read_db().expect("can fetch pet list")
+            }
+        }
+    }
+}
+
+/// Does what `command` does besides running actions, and returns the actions it runs. `None`
+/// if it failed and the commands after it shouldn't run, as `:wq` doesn't quit when the write
+/// fails.
+fn prepare<T: Stored + Aged>(
+    command: Command,
+    store: &Store,
+    state: &mut AppState<T>,
+) -> Result<Option<Vec<Action>>, Error> {
+    let on_data = matches!(state.active_menu_item, MenuItem::Data);
+    let actions = match command {
+        Command::Run(action) => vec![action],
+        Command::Search(query) => {
+            state.search = Some(query);
+            vec![Action::SearchNext]
+        }
+        Command::Sort {
+            by: None,
+            reverse: false,
+        } => vec![Action::Sort],
+        Command::Sort { by, reverse } => {
+            let sort = match by {
+                Some(by) => Some(SortKey::parse::<T>(&by)),
+                None => state.sort.map(Ok),
+            };
+            match sort {
+                Some(Ok(sort)) if reverse => state.sort_by(Some(sort.reversed())),
+                Some(Ok(sort)) => state.sort_by(Some(sort)),
+                Some(Err(e)) => state.status = Some(e.to_string()),
+                None => {}
+            }
+            Vec::new()
+        }
+        Command::Write(None) => {
+            let path = store.path.display();
+            state.status = Some(format!("every change is written to {} right away", path));
+            Vec::new()
+        }
+        Command::Write(Some(path)) => {
+            if !export_rows(state, &path) {
+                return Ok(None);
+            }
+            Vec::new()
+        }
+        Command::GoTo(row) => {
+            let row = row.min(state.list_len()).max(1);
+            state.select_row(store, row - 1)?;
+            Vec::new()
+        }
+        Command::Visual(on) => {
+            state.visual = state.data_list_state.selected().filter(|_| on && on_data);
+            Vec::new()
+        }
+        Command::DeleteRows(count) if on_data && count > 1 => {
+            let last = state.data.len().saturating_sub(1);
+            let selected = state.data_list_state.selected();
+            state.visual = selected.map(|idx| (idx + count - 1).min(last));
+            vec![Action::Delete]
+        }
+        Command::DeleteRows(count) => vec![Action::Delete; count.min(state.list_len())],
+        Command::Unknown(text) => {
+            state.status = Some(format!("not a command: {}", text));
+            Vec::new()
+        }
+    };
+    Ok(Some(actions))
+}
+
+/// Writes the rows of the data screen, in the order they are listed, to a CSV file. `false` if
+/// that failed; the status line says why.
+fn export_rows<T: Stored>(state: &mut AppState<T>, path: &str) -> bool {
+    let result = csv_io::export(path, &state.data);
+    state.status = Some(match &result {
+        Ok(()) => format!("wrote {} rows to {}", state.data.len(), path),
+        Err(e) => e.to_string(),
+    });
+    result.is_ok()
+}
+
+/// Carries out `action` on the screen `state` shows, whichever key or command asked for it.
+/// `Some` when the TUI for this kind of row has to be left.
+fn perform<T: Stored + Aged, B: Backend>(
+    action: Action,
+    store: &Store,
+    config: &mut Config,
+    keymap: &Keymap,
+    rx: &Receiver<Event<KeyEvent>>,
+    terminal: &mut Terminal<B>,
+    state: &mut AppState<T>,
+) -> Result<Option<Exit>, Box<dyn std::error::Error>> {
+    let on_delete = config.ownership.on_child_delete;
+    let age_bounds = config.ages.of(T::entity());
+
+    match action {
+        Action::Quit => return Ok(Some(Exit::Quit)),
+        Action::Calendar => state.active_menu_item = MenuItem::Calendar,
+        Action::Stats => state.active_menu_item = MenuItem::Stats,
+        Action::Left if matches!(state.active_menu_item, MenuItem::Calendar) => {
+            state.calendar.move_days(-1);
+        }
+        Action::Right if matches!(state.active_menu_item, MenuItem::Calendar) => {
+            state.calendar.move_days(1);
+        }
+        Action::Up if matches!(state.active_menu_item, MenuItem::Calendar) => {
+            state.calendar.move_days(-7);
+        }
+        Action::Down if matches!(state.active_menu_item, MenuItem::Calendar) => {
+            state.calendar.move_days(7);
+        }
+        Action::PageUp if matches!(state.active_menu_item, MenuItem::Calendar) => {
+            state.calendar.move_months(-1);
+        }
+        Action::PageDown if matches!(state.active_menu_item, MenuItem::Calendar) => {
+            state.calendar.move_months(1);
+        }
+        Action::NextField if matches!(state.active_menu_item, MenuItem::Calendar) => {
+            state.calendar.next_field(&state.data);
+        }
+        Action::SearchNext if matches!(state.active_menu_item, MenuItem::Calendar) => {
+            state.calendar.select_next();
+        }
+        Action::SearchPrevious if matches!(state.active_menu_item, MenuItem::Calendar) => {
+            state.calendar.select_previous();
+        }
+        Action::Confirm if matches!(state.active_menu_item, MenuItem::Calendar) => {
+            if let Some(idx) = state.calendar.selected() {
+                state.data_list_state.select(Some(idx));
+                state.active_menu_item = MenuItem::Data;
+            }
+        }
+        Action::Add
+        | Action::Delete
+        | Action::Edit
+        | Action::Merge
+        | Action::Owner
+        | Action::Restore
+        | Action::Date
+        | Action::Left
+        | Action::Right
+            if state.as_of.is_some() => {}
+        Action::Back if state.as_of.is_some() => state.back_to_live(store)?,
+        Action::Home => state.active_menu_item = MenuItem::Home,
+        Action::Pets | Action::Children => {
+            let to = if action == Action::Pets {
+                Entity::Pets
+            } else {
+                Entity::Children
+            };
+            if to != T::entity() {
+                // The screens of the other kind read the data afresh; stay here if it can't be.
+                if let Err(e) = store.read() {
+                    state.status = Some(e.to_string());
+                    return Ok(None);
                 }
-                KeyCode::Down => {
-                    if let Some(selected) = pet_list_state.selected() {
-                        let amount_pets = read_db().expect("can fetch pet list").len();
-                        if selected >= amount_pets - 1 {
-                            pet_list_state.select(Some(0));
-                        } else {
-                            pet_list_state.select(Some(selected + 1));
-                        }
-                    }
+                let as_of = state.as_of;
+                return Ok(Some(Exit::Switch { to, as_of }));
+            }
+            state.active_menu_item = MenuItem::Data;
+        }
+        Action::Trash => state.active_menu_item = MenuItem::Trash,
+        Action::Upcoming => state.active_menu_item = MenuItem::Upcoming,
+        Action::History => {
+            state.load_history(store);
+            state.active_menu_item = MenuItem::History;
+        }
+        Action::Down if matches!(state.active_menu_item, MenuItem::History) => {
+            select_next(&mut state.history_list_state, state.history.len());
+        }
+        Action::Up if matches!(state.active_menu_item, MenuItem::History) => {
+            select_previous(&mut state.history_list_state, state.history.len());
+        }
+        Action::Backups => match BackupsView::load(store) {
+            Ok(view) => {
+                state.backups = view;
+                state.active_menu_item = MenuItem::Backups;
+            }
+            Err(e) => state.status = Some(e.to_string()),
+        },
+        Action::Confirm if matches!(state.active_menu_item, MenuItem::History) => {
+            let selected = state.history_list_state.selected();
+            if let Some(entry) = selected.and_then(|i| state.history.get(i)) {
+                let (at, id) = (entry.at, entry.record_id);
+                state.view_as_of(store, at)?;
+                if let Some(idx) = state.data.iter().position(|row| row.id() == id) {
+                    state.data_list_state.select(Some(idx));
+                }
+                state.active_menu_item = MenuItem::Data;
+            }
+        }
+        Action::OlderVersion => state.older_version(store)?,
+        Action::NewerVersion => state.newer_version(),
+        Action::Down if matches!(state.active_menu_item, MenuItem::Backups) => {
+            if let Err(e) = state.backups.select_next(store) {
+                state.status = Some(e.to_string());
+            }
+        }
+        Action::Up if matches!(state.active_menu_item, MenuItem::Backups) => {
+            if let Err(e) = state.backups.select_previous(store) {
+                state.status = Some(e.to_string());
+            }
+        }
+        Action::Restore if matches!(state.active_menu_item, MenuItem::Backups) => {
+            if let Some(backup) = state.backups.selected() {
+                let restored = backups::restore(store, backup);
+                state.apply_logged(restored);
+                match BackupsView::load(store) {
+                    Ok(view) => state.backups = view,
+                    Err(e) => state.status = Some(e.to_string()),
                 }
-                KeyCode::Up => {
-                    if let Some(selected) = pet_list_state.selected() {
-                        let amount_pets = read_db().expect("can fetch pet list").len();
-                        if selected > 0 {
-                            pet_list_state.select(Some(selected - 1));
-                        } else {
-                            pet_list_state.select(Some(amount_pets - 1));
-                        }
+            }
+        }
+        Action::Categories => state.active_menu_item = MenuItem::Categories,
+        Action::Down if matches!(state.active_menu_item, MenuItem::Categories) => {
+            let len = state.categories.categories.len();
+            select_next(&mut state.categories.list_state, len);
+        }
+        Action::Up if matches!(state.active_menu_item, MenuItem::Categories) => {
+            let len = state.categories.categories.len();
+            select_previous(&mut state.categories.list_state, len);
+        }
+        Action::Back if state.categories.merging.is_some() => {
+            state.categories.merging = None;
+        }
+        Action::Confirm if state.categories.merging.is_some() => {
+            let from = state.categories.merging.take().expect("merge was started");
+            if let Some(into) = state.categories.selected().cloned() {
+                let changed = change_categories(store, |db| db.merge_categories(&from, &into));
+                state.apply_logged(changed);
+            }
+        }
+        Action::Add if matches!(state.active_menu_item, MenuItem::Categories) => {
+            let name = prompt_input(rx, terminal, keymap, state, "New category", "")?;
+            if let Some(name) = name {
+                let changed = change_categories(store, |db| db.add_category(&name).map(|_| ()));
+                state.apply_logged(changed);
+            }
+        }
+        Action::Edit if matches!(state.active_menu_item, MenuItem::Categories) => {
+            if let Some(from) = state.categories.selected().cloned() {
+                let title = format!("Rename '{}'", from);
+                let initial = from.to_string();
+                let name = prompt_input(rx, terminal, keymap, state, &title, &initial)?;
+                if let Some(name) = name {
+                    let changed =
+                        change_categories(store, |db| db.rename_category(&from, &name).map(|_| ()));
+                    state.apply_logged(changed);
+                }
+            }
+        }
+        Action::Merge if matches!(state.active_menu_item, MenuItem::Categories) => {
+            state.categories.merging = state.categories.selected().cloned();
+        }
+        Action::Delete if matches!(state.active_menu_item, MenuItem::Categories) => {
+            if let Some(category) = state.categories.selected().cloned() {
+                let changed = change_categories(store, |db| db.delete_category(&category));
+                state.apply_logged(changed);
+            }
+        }
+        Action::Down if matches!(state.active_menu_item, MenuItem::Trash) => {
+            select_next(&mut state.trash_list_state, state.trash.len());
+        }
+        Action::Up if matches!(state.active_menu_item, MenuItem::Trash) => {
+            select_previous(&mut state.trash_list_state, state.trash.len());
+        }
+        Action::Restore if matches!(state.active_menu_item, MenuItem::Trash) => {
+            if let Some(id) = state.selected_trash().map(DataRow::id) {
+                state.apply_logged(restore_from_trash::<T>(store, id));
+            }
+        }
+        Action::Delete if matches!(state.active_menu_item, MenuItem::Trash) => {
+            if let Some(id) = state.selected_trash().map(DataRow::id) {
+                state.apply_logged(purge_from_trash::<T>(store, id, on_delete));
+            }
+        }
+        Action::Add => {
+            state.apply_logged(add_random_to_db::<T>(store, age_bounds));
+        }
+        Action::Delete => {
+            let selected = state.data_list_state.selected().map(|idx| (idx, idx));
+            if let Some((first, last)) = state.visual_range().or(selected) {
+                let ids: Vec<usize> = state.data[first..=last].iter().map(DataRow::id).collect();
+                let mut moved_any = false;
+                for id in ids {
+                    let moved = move_to_trash::<T>(store, id, on_delete);
+                    let refused = moved.is_err();
+                    moved_any |= !refused;
+                    state.apply_logged(moved);
+                    if refused {
+                        break;
                     }
                 }
-                KeyCode::Char('e') => {
-                    if let Some(selected) = pet_list_state.selected() {
-                        let mut new_name = String::with_capacity(8);
-                        let change_loaded = |pet_list: &mut [Pet], idx: WhichRow, new_str: &str| {
-                            pet_list[idx].name = new_str.to_string()
-                        };
-                        word_input(
-                            &rx,
-                            &mut new_name,
-                            &mut loaded_pets,
-                            selected,
-                            &mut terminal,
-                            &menu_titles,
-                            active_menu_item,
-                            &mut pet_list_state,
-                            change_loaded,
-                        )?;
-                        if new_name.is_empty() {
-                            let rng = rand::thread_rng();
-                            new_name = rng.sample_iter(Alphanumeric).take(10).collect();
-                        }
-                        edit_pet_at_index(&mut pet_list_state, Some(new_name), 0, &mut loaded_pets)
-                            .expect("can edit pet");
+                state.visual = None;
+                if moved_any {
+                    state.data_list_state.select(Some(first.saturating_sub(1)));
+                    clamp_selection(&mut state.data_list_state, state.data.len());
+                }
+            }
+        }
+        Action::Owner
+            if T::entity() == Entity::Pets && matches!(state.active_menu_item, MenuItem::Data) =>
+        {
+            if let Some(pet_id) = state.selected_data().map(DataRow::id) {
+                let children = Child::live(&state.db);
+                let owner = state.db.pets.iter().find(|pet| pet.id == pet_id);
+                let current = owner.and_then(|pet| pet.owner);
+                let mut options = vec!["(nobody)".to_owned()];
+                options.extend(children.iter().map(|c| format!("{} ({})", c.name, c.id)));
+                let selected = current
+                    .and_then(|id| children.iter().position(|c| c.id == id))
+                    .map_or(0, |idx| idx + 1);
+                let picked = pick_from(rx, terminal, keymap, state, "Owner", options, selected)?;
+                if let Some(picked) = picked {
+                    let owner = picked.checked_sub(1).map(|idx| children[idx].id);
+                    state.apply_logged(set_owner(store, pet_id, owner));
+                }
+            }
+        }
+        Action::Date if matches!(state.active_menu_item, MenuItem::Data) => {
+            if let Some(row) = state.selected_data().cloned() {
+                let fields: Vec<&Field> = T::fields()
+                    .iter()
+                    .filter(|f| f.kind == FieldKind::Timestamp)
+                    .collect();
+                let field = match fields.len() {
+                    1 => Some(0),
+                    _ => pick_from(
+                        rx,
+                        terminal,
+                        keymap,
+                        state,
+                        "Date to change",
+                        fields.iter().map(|f| f.label.to_owned()).collect(),
+                        0,
+                    )?,
+                };
+                let field = field.map(|idx| fields[idx]);
+                let current = field.and_then(|f| Some((f, row.timestamp(f.name)?)));
+                if let Some((field, current)) = current {
+                    let title = format!("{} of {}", field.label, row.name());
+                    let picked = pick_datetime(rx, terminal, keymap, state, &title, current)?;
+                    if let Some(at) = picked {
+                        let changed =
+                            set_timestamp::<T>(store, row.id(), field.name, at, age_bounds);
+                        state.apply_logged(changed);
                     }
                 }
-                KeyCode::Left => {
-                    edit_pet_at_index(&mut pet_list_state, None, -1, &mut loaded_pets)
-                        .expect("can edit pet");
+            }
+        }
+        Action::Top | Action::Bottom => {
+            let idx = match action {
+                Action::Top => 0,
+                _ => state.list_len().saturating_sub(1),
+            };
+            state.select_row(store, idx)?;
+        }
+        Action::PageUp | Action::PageDown => {
+            if let Some(selected) = state.selected_row() {
+                let page = state.hits.page();
+                let idx = match action {
+                    Action::PageUp => selected.saturating_sub(page),
+                    _ => (selected + page).min(state.list_len().saturating_sub(1)),
+                };
+                state.select_row(store, idx)?;
+            }
+        }
+        Action::Search => {
+            let initial = state.search.clone().unwrap_or_default();
+            let query = prompt_input(rx, terminal, keymap, state, "Search", &initial)?;
+            if let Some(query) = query.filter(|query| !query.is_empty()) {
+                state.search = Some(query);
+                return perform(
+                    Action::SearchNext,
+                    store,
+                    config,
+                    keymap,
+                    rx,
+                    terminal,
+                    state,
+                );
+            }
+        }
+        Action::SearchNext | Action::SearchPrevious => match state.search.clone() {
+            Some(query) => {
+                state.active_menu_item = MenuItem::Data;
+                if !state.find(&query, action == Action::SearchNext) {
+                    state.status = Some(format!(
+                        "no {} matches '{}'",
+                        T::row_name().to_lowercase(),
+                        query
+                    ));
                 }
-                KeyCode::Right => {
-                    edit_pet_at_index(&mut pet_list_state, None, 1, &mut loaded_pets)
-                        .expect("can edit pet");
+            }
+            None => state.status = Some("nothing to search for yet".to_owned()),
+        },
+        Action::Palette => {
+            if let Some(chosen) = pick_action(rx, terminal, keymap, state)? {
+                return perform(chosen, store, config, keymap, rx, terminal, state);
+            }
+        }
+        Action::Theme => {
+            let labels = Preset::ALL.iter().map(Preset::to_string).collect();
+            let current = Preset::ALL
+                .iter()
+                .position(|&preset| preset == config.theme.preset)
+                .unwrap_or(0);
+            let picked = pick_from(rx, terminal, keymap, state, "Theme", labels, current)?;
+            if let Some(picked) = picked {
+                config.theme.preset = Preset::ALL[picked];
+                state.theme = Theme::new(&config.theme);
+            }
+        }
+        Action::ShrinkList | Action::GrowList => {
+            let drawn = state.hits.list_width();
+            let wider = action == Action::GrowList;
+            state.list_width = Some(resized_list_width(state.list_width, drawn, wider));
+            config.tui.list_width = state.list_width;
+            if let Err(e) = config.save_list_width() {
+                state.status = Some(format!("could not save the list width: {}", e));
+            }
+        }
+        Action::Export => {
+            let initial = format!("{}.csv", T::title().to_lowercase());
+            let path = prompt_input(rx, terminal, keymap, state, "Export to", &initial)?;
+            if let Some(path) = path {
+                export_rows(state, &path);
+            }
+        }
+        Action::Sort => {
+            let options = SortKey::options::<T>();
+            let mut labels = vec!["(as stored)".to_owned()];
+            labels.extend(options.iter().map(|option| option.to_string()));
+            let picked = pick_from(rx, terminal, keymap, state, "Sort by", labels, 0)?;
+            if let Some(picked) = picked {
+                let sort = picked.checked_sub(1).map(|idx| {
+                    SortKey::parse::<T>(options[idx]).expect("options can be sorted by")
+                });
+                state.sort_by(sort);
+            }
+        }
+        Action::Down => select_next(&mut state.data_list_state, state.data.len()),
+        Action::Up => select_previous(&mut state.data_list_state, state.data.len()),
+        Action::Edit => {
+            if let Some(selected) = state.data_list_state.selected() {
+                let mut new_name = String::with_capacity(8);
+                let change_loaded =
+                    |rows: &mut [T], idx: WhichRow, new_str: &str| rows[idx].set_name(new_str);
+                word_input(
+                    rx,
+                    &mut new_name,
+                    selected,
+                    terminal,
+                    keymap,
+                    state,
+                    change_loaded,
+                )?;
+                if new_name.is_empty() {
+                    let rng = rand::thread_rng();
+                    new_name = rng.sample_iter(Alphanumeric).take(10).collect();
                 }
-                _ => {}
-            },
-            Event::Tick => {}
+                let id = state.data[selected].id();
+                state.apply_logged(edit_row::<T>(store, id, Some(new_name), 0, age_bounds));
+            }
+        }
+        Action::Left => {
+            if let Some(row) = state.selected_data() {
+                state.apply_logged(edit_row::<T>(store, row.id(), None, -1, age_bounds));
+            }
         }
+        Action::Right => {
+            if let Some(row) = state.selected_data() {
+                state.apply_logged(edit_row::<T>(store, row.id(), None, 1, age_bounds));
+            }
+        }
+        _ => {}
     }
+    Ok(None)
+}
 
-    if cfg!(target_os = "windows") {
-        std::process::Command::new("cmd")
-            .args(["/c", "cls"])
-            .spawn()
-            .expect("cls command failed to start")
-            .wait()
-            .expect("failed to wait");
-    } else {
-        std::process::Command::new("clear")
-            .spawn()
-            .expect("clear command failed to start")
-            .wait()
-            .expect("failed to wait");
-    };
+fn add_random_to_db<T: Stored + Aged>(
+    store: &Store,
+    age_bounds: &AgeBounds,
+) -> Result<Logged, Error> {
+    let mut parsed = store.read()?;
 
-    Ok(())
+    let mut random_row = T::create_placeholder();
+    random_row.fit_into(&parsed);
+    random_row.fit_age(age_bounds);
+
+    T::rows_mut(&mut parsed).push(random_row.clone());
+    store.write(&parsed)?;
+    let log = audit::record(store, audit::Action::Insert, None, Some(&random_row));
+    Ok(Logged { db: parsed, log })
 }
 
-fn read_db() -> Result<Vec<Pet>, Error> {
-    let db_content = fs::read_to_string(DB_PATH)?;
-    let parsed: Vec<Pet> = serde_json::from_str(&db_content)?;
-    Ok(parsed)
+fn move_to_trash<T: Stored>(
+    store: &Store,
+    id: usize,
+    on_delete: OnDelete,
+) -> Result<Logged, Error> {
+    let mut parsed = store.read()?;
+    if let Some(idx) = T::position(&parsed, id) {
+        let before = parsed.clone();
+        T::release(&mut parsed, id, on_delete, false)?;
+        T::rows_mut(&mut parsed)[idx].set_deleted_at(Some(Utc::now().round_subsecs(0)));
+        store.write(&parsed)?;
+        let log = audit::record_changes(store, &before, &parsed);
+        return Ok(Logged { db: parsed, log });
+    }
+    Ok(Logged::unchanged(parsed))
 }
 
-fn add_random_pet_to_db() -> Result<Vec<Pet>, Error> {
-    let db_content = fs::read_to_string(DB_PATH)?;
-    let mut parsed: Vec<Pet> = serde_json::from_str(&db_content)?;
+fn restore_from_trash<T: Stored>(store: &Store, id: usize) -> Result<Logged, Error> {
+    let mut parsed = store.read()?;
+    if let Some(idx) = T::position(&parsed, id) {
+        let before = T::rows(&parsed)[idx].clone();
+        T::rows_mut(&mut parsed)[idx].set_deleted_at(None);
+        store.write(&parsed)?;
+        let log = audit::record(
+            store,
+            audit::Action::Update,
+            Some(&before),
+            Some(&T::rows(&parsed)[idx]),
+        );
+        return Ok(Logged { db: parsed, log });
+    }
+    Ok(Logged::unchanged(parsed))
+}
 
-    let random_pet = Pet::create_placeholder();
+fn purge_from_trash<T: Stored>(
+    store: &Store,
+    id: usize,
+    on_delete: OnDelete,
+) -> Result<Logged, Error> {
+    let mut parsed = store.read()?;
+    if T::position(&parsed, id).is_some() {
+        let before = parsed.clone();
+        T::release(&mut parsed, id, on_delete, true)?;
+        if let Some(idx) = T::position(&parsed, id) {
+            T::rows_mut(&mut parsed).remove(idx);
+        }
+        store.write_after_backup(&parsed)?;
+        let log = audit::record_changes(store, &before, &parsed);
+        return Ok(Logged { db: parsed, log });
+    }
+    Ok(Logged::unchanged(parsed))
+}
 
-    parsed.push(random_pet);
-    fs::write(DB_PATH, serde_json::to_vec(&parsed)?)?;
-    Ok(parsed)
+fn set_owner(store: &Store, pet_id: usize, owner: Option<usize>) -> Result<Logged, Error> {
+    let before = store.read()?;
+    let mut parsed = before.clone();
+    parsed.set_owner(pet_id, owner)?;
+    store.write(&parsed)?;
+    let log = audit::record_changes(store, &before, &parsed);
+    Ok(Logged { db: parsed, log })
 }
 
-fn remove_pet_at_index(pet_list_state: &mut ListState) -> Result<(), Error> {
-    if let Some(selected) = pet_list_state.selected() {
-        let db_content = fs::read_to_string(DB_PATH)?;
-        let mut parsed: Vec<Pet> = serde_json::from_str(&db_content)?;
-        parsed.remove(selected);
-        fs::write(DB_PATH, serde_json::to_vec(&parsed)?)?;
-        let _amount_pets = read_db().expect("can fetch pet list").len();
-        if selected > 0 {
-            pet_list_state.select(Some(selected - 1));
-        } else {
-            pet_list_state.select(Some(0));
+/// Sets the `Timestamp` field `field` of the row `id` to `at`, as long as that leaves its age
+/// within `age_bounds` the way `Aged::shift_age` does.
+fn set_timestamp<T: Stored + Aged>(
+    store: &Store,
+    id: usize,
+    field: &str,
+    at: DateTime<Utc>,
+    age_bounds: &AgeBounds,
+) -> Result<Logged, Error> {
+    let mut parsed = store.read()?;
+    if let Some(idx) = T::position(&parsed, id) {
+        let before = T::rows(&parsed)[idx].clone();
+        let mut value = serde_json::to_value(&before)?;
+        value[field] = serde_json::to_value(at.round_subsecs(0))?;
+        let after: T = serde_json::from_value(value)?;
+        let (from, to) = (before.age(age_bounds.unit), after.age(age_bounds.unit));
+        if from != to && !age_bounds.allows(from, to) {
+            return Err(age_bounds.refuse(to));
         }
+        T::rows_mut(&mut parsed)[idx] = after;
+        store.write(&parsed)?;
+        let log = audit::record(
+            store,
+            audit::Action::Update,
+            Some(&before),
+            Some(&T::rows(&parsed)[idx]),
+        );
+        return Ok(Logged { db: parsed, log });
     }
-    Ok(())
+    Ok(Logged::unchanged(parsed))
 }
 
-fn edit_pet_at_index(
-    pet_list_state: &mut ListState,
+/// Applies a change to the managed categories, pets included when it moves them.
+fn change_categories(
+    store: &Store,
+    change: impl FnOnce(&mut Database) -> Result<(), Error>,
+) -> Result<Logged, Error> {
+    let before = store.read()?;
+    let mut parsed = before.clone();
+    change(&mut parsed)?;
+    store.write_after_backup(&parsed)?;
+    let log = audit::record_changes(store, &before, &parsed);
+    Ok(Logged { db: parsed, log })
+}
+
+fn edit_row<T: Stored + Aged>(
+    store: &Store,
+    id: usize,
     name_change: Option<String>,
     age_shift: i8,
-    pet_list: &mut [Pet],
-) -> Result<(), Error> {
-    if let Some(selected) = pet_list_state.selected() {
-        let db_content = fs::read_to_string(DB_PATH)?;
-        let mut parsed: Vec<Pet> = serde_json::from_str(&db_content)?;
+    age_bounds: &AgeBounds,
+) -> Result<Logged, Error> {
+    let mut parsed = store.read()?;
+    if let Some(idx) = T::position(&parsed, id) {
+        let before = T::rows(&parsed)[idx].clone();
+        let row = &mut T::rows_mut(&mut parsed)[idx];
+        let renamed = name_change.is_some();
         if let Some(new_name) = name_change {
-            parsed[selected].set_name(new_name.clone());
-            pet_list[selected].set_name(new_name);
+            row.set_name(new_name);
         }
-        match age_shift {
-            z if z > 0 => {
-                parsed[selected].incr_age(z as u8);
-                pet_list[selected].set_age(parsed[selected].age());
-            }
-            w if w < 0 => {
-                parsed[selected].decr_age((-w) as u8);
-                pet_list[selected].set_age(parsed[selected].age());
-            }
-            _ => {}
+        if age_shift != 0 {
+            row.shift_age(age_shift, age_bounds)?;
+        }
+        // Like a rename to the same name: nothing to back up or log.
+        if serde_json::to_value(&before)? == serde_json::to_value(&*row)? {
+            return Ok(Logged::unchanged(parsed));
+        }
+        if renamed {
+            store.write_after_backup(&parsed)?;
+        } else {
+            store.write(&parsed)?;
         }
-        fs::write(DB_PATH, serde_json::to_vec(&parsed)?)?;
-        let _amount_pets = read_db().expect("can fetch pet list").len();
+        let log = audit::record(
+            store,
+            audit::Action::Update,
+            Some(&before),
+            Some(&T::rows(&parsed)[idx]),
+        );
+        return Ok(Logged { db: parsed, log });
     }
-    Ok(())
+    Ok(Logged::unchanged(parsed))
 }
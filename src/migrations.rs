@@ -0,0 +1,234 @@
+use std::collections::BTreeSet;
+use std::convert::TryFrom;
+
+use chrono::prelude::*;
+use serde_json::{json, Value};
+
+use crate::category::DEFAULT_CATEGORIES;
+use crate::data_row::AgeUnit;
+use crate::db_stuff::Error;
+
+pub const CURRENT_VERSION: u64 = 3;
+
+/// Upgrades a raw DB document from `from` to `from + 1`.
+/// Anything worth telling the user about during a dry run gets pushed onto the notes.
+pub struct Migration {
+    pub from: u64,
+    pub description: &'static str,
+    pub apply: fn(Value, &mut Vec<String>) -> Result<Value, Error>,
+}
+
+pub const MIGRATIONS: &[Migration] = &[
+    Migration {
+        from: 0,
+        description: "wrap the bare pet array in a versioned envelope",
+        apply: wrap_bare_array,
+    },
+    Migration {
+        from: 1,
+        description: "list the categories the pets use as managed categories",
+        apply: collect_categories,
+    },
+    Migration {
+        from: 2,
+        description: "derive ages from birth dates, back-filling those from age and created_at",
+        apply: derive_ages,
+    },
+];
+
+/// Files from before the envelope existed are a bare array of pets, which we call version 0.
+pub fn detect_version(raw: &Value) -> Result<u64, Error> {
+    match raw {
+        Value::Array(_) => Ok(0),
+        Value::Object(map) => map
+            .get("version")
+            .and_then(Value::as_u64)
+            .ok_or_else(|| Error::Migration("missing or invalid \"version\" field".to_owned())),
+        _ => Err(Error::Migration(
+            "expected an array or an object at the top level".to_owned(),
+        )),
+    }
+}
+
+pub fn upgrade(mut raw: Value, notes: &mut Vec<String>) -> Result<Value, Error> {
+    let mut version = detect_version(&raw)?;
+    if version > CURRENT_VERSION {
+        return Err(Error::UnsupportedVersion {
+            found: version,
+            supported: CURRENT_VERSION,
+        });
+    }
+    while version < CURRENT_VERSION {
+        let migration = MIGRATIONS
+            .iter()
+            .find(|m| m.from == version)
+            .ok_or_else(|| Error::Migration(format!("no migration from version {}", version)))?;
+        notes.push(format!(
+            "v{} -> v{}: {}",
+            version,
+            version + 1,
+            migration.description
+        ));
+        raw = (migration.apply)(raw, notes)?;
+        version += 1;
+        raw["version"] = json!(version);
+    }
+    Ok(raw)
+}
+
+fn wrap_bare_array(raw: Value, notes: &mut Vec<String>) -> Result<Value, Error> {
+    let pets = match raw {
+        Value::Array(pets) => pets,
+        _ => return Err(Error::Migration("expected a bare array of pets".to_owned())),
+    };
+    notes.push(format!(
+        "  {} pets moved under \"pets\", \"children\" starts empty",
+        pets.len()
+    ));
+    Ok(json!({ "version": 1, "pets": pets, "children": [] }))
+}
+
+fn collect_categories(mut raw: Value, notes: &mut Vec<String>) -> Result<Value, Error> {
+    let mut categories = BTreeSet::new();
+    if let Some(pets) = raw["pets"].as_array_mut() {
+        for pet in pets {
+            // Pets without a category go in the first default one, a category needs a name.
+            let category = pet["category"]
+                .as_str()
+                .map(|c| c.trim().to_lowercase())
+                .filter(|c| !c.is_empty())
+                .unwrap_or_else(|| DEFAULT_CATEGORIES[0].to_owned());
+            if pet["category"] != json!(category) {
+                notes.push(format!(
+                    "  pet {}: category {} -> \"{}\"",
+                    pet["id"], pet["category"], category
+                ));
+                pet["category"] = json!(category);
+            }
+            categories.insert(category);
+        }
+    }
+    if categories.is_empty() {
+        categories.extend(DEFAULT_CATEGORIES.iter().map(|c| c.to_string()));
+    }
+    notes.push(format!(
+        "  categories: {}",
+        categories.iter().cloned().collect::<Vec<_>>().join(", ")
+    ));
+    raw["categories"] = json!(categories);
+    Ok(raw)
+}
+
+fn derive_ages(mut raw: Value, notes: &mut Vec<String>) -> Result<Value, Error> {
+    for (kind, key) in [("pet", "pets"), ("child", "children")] {
+        if let Some(rows) = raw[key].as_array_mut() {
+            for row in rows {
+                if let Some(note) = backfill_birthdate(row)? {
+                    notes.push(format!("  {} {}: {}", kind, row["id"], note));
+                }
+            }
+        }
+    }
+    Ok(raw)
+}
+
+/// Replaces the stored `age` of a row (in years, as of its `created_at`, or of now without one)
+/// with a `birthdate`, unless it already has one. Rows logged to the audit log before version 3
+/// go through this too. Returns what was done, if it's worth a note.
+pub fn backfill_birthdate(row: &mut Value) -> Result<Option<String>, Error> {
+    let row = match row.as_object_mut() {
+        Some(row) => row,
+        None => return Ok(None),
+    };
+    let age = match row.remove("age") {
+        Some(age) => age,
+        None => return Ok(None),
+    };
+    if let Some(birthdate) = row.get("birthdate") {
+        let birthdate: DateTime<Utc> = serde_json::from_value(birthdate.clone())?;
+        let derived = AgeUnit::Years.between(birthdate, Utc::now());
+        return Ok(match age.as_u64() {
+            Some(age) if age == u64::from(derived) => None,
+            _ => Some(format!(
+                "stored age {} dropped, the birthdate makes it {}",
+                age, derived
+            )),
+        });
+    }
+    let years = age
+        .as_u64()
+        .and_then(|age| i64::try_from(age).ok())
+        .ok_or_else(|| Error::Migration(format!("age {} is not a whole number", age)))?;
+    let reference = match row.get("created_at") {
+        Some(created_at) => serde_json::from_value(created_at.clone())?,
+        None => Utc::now().round_subsecs(0),
+    };
+    let birthdate = AgeUnit::Years
+        .shift(reference, -years)
+        .ok_or_else(|| Error::Migration(format!("age {} is out of range", years)))?;
+    row.insert("birthdate".to_owned(), json!(birthdate));
+    Ok(Some(format!(
+        "born {}, {} years before {}",
+        birthdate.format("%Y-%m-%d"),
+        years,
+        reference.format("%Y-%m-%d")
+    )))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db_stuff::Database;
+
+    /// A file from before the envelope, with a category that needs tidying and one missing.
+    fn v0_fixture() -> Value {
+        json!([
+            {"id": 2, "name": "Nacho", "category": "Cats ", "age": 2, "created_at": "2020-08-20T12:00:00Z"},
+            {"id": 3, "name": "Tacos", "category": "dogs", "age": 4, "created_at": "2019-03-16T12:00:00Z"},
+            {"id": 4, "name": "Pepper", "age": 3, "created_at": "2019-05-22T12:00:00Z"}
+        ])
+    }
+
+    #[test]
+    fn upgrades_a_bare_array_to_the_current_version() {
+        let mut notes = Vec::new();
+        let upgraded = upgrade(v0_fixture(), &mut notes).unwrap();
+
+        assert_eq!(upgraded["version"], json!(CURRENT_VERSION));
+        assert_eq!(upgraded["children"], json!([]));
+        assert_eq!(upgraded["categories"], json!(["cats", "dogs"]));
+        let pets = upgraded["pets"].as_array().unwrap();
+        assert_eq!(pets[0]["category"], json!("cats"));
+        assert_eq!(pets[2]["category"], json!("cats"));
+        assert_eq!(pets[0]["birthdate"], json!("2018-08-20T12:00:00Z"));
+        assert_eq!(pets[1]["birthdate"], json!("2015-03-16T12:00:00Z"));
+        assert!(pets.iter().all(|pet| pet.get("age").is_none()));
+        for from in 0..CURRENT_VERSION {
+            let step = format!("v{} -> v{}", from, from + 1);
+            assert!(notes.iter().any(|note| note.starts_with(&step)), "{}", step);
+        }
+
+        let db: Database = serde_json::from_value(upgraded).unwrap();
+        assert_eq!(db.pets.len(), 3);
+        assert_eq!(db.pets[1].name, "Tacos");
+    }
+
+    #[test]
+    fn leaves_a_current_file_alone() {
+        let current = upgrade(v0_fixture(), &mut Vec::new()).unwrap();
+        let mut notes = Vec::new();
+        assert_eq!(upgrade(current.clone(), &mut notes).unwrap(), current);
+        assert!(notes.is_empty());
+    }
+
+    #[test]
+    fn refuses_files_from_a_newer_version() {
+        let newer = json!({ "version": CURRENT_VERSION + 1, "pets": [], "children": [] });
+        assert!(matches!(
+            upgrade(newer, &mut Vec::new()),
+            Err(Error::UnsupportedVersion { .. })
+        ));
+        assert!(detect_version(&json!("pets")).is_err());
+        assert!(detect_version(&json!({ "pets": [] })).is_err());
+    }
+}
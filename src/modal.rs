@@ -0,0 +1,355 @@
+use crossterm::event::{KeyCode, KeyEvent};
+use serde::{Deserialize, Serialize};
+
+use crate::keymap::{Action, KeyBinding, Keymap};
+
+/// How keys are read in the TUI: straight through the keymap, or vi-style with counts,
+/// two-key commands, a search line and a command line on top of it.
+#[derive(Serialize, Deserialize, Copy, Clone, Debug, PartialEq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum Navigation {
+    #[default]
+    Keys,
+    Modal,
+}
+
+/// What a key press asks the TUI to do.
+#[derive(Debug, PartialEq)]
+pub enum Command {
+    Run(Action),
+    /// Select the next row whose name contains the text.
+    Search(String),
+    /// Sort the rows by a field, or pick one if there is none. `reverse` flips the order.
+    Sort {
+        by: Option<String>,
+        reverse: bool,
+    },
+    /// Export the rows to a CSV file, if one is named.
+    Write(Option<String>),
+    /// Select the row with this number, counting from 1, or the last one if there are fewer.
+    GoTo(usize),
+    /// Start or stop selecting a range of rows.
+    Visual(bool),
+    /// Delete this many rows, starting with the selected one.
+    DeleteRows(usize),
+    /// A command line that didn't make sense.
+    Unknown(String),
+}
+
+/// Turns key presses into commands, the way `[tui] navigation` says.
+pub enum KeyHandler {
+    Keys,
+    Modal(Modal),
+}
+
+impl KeyHandler {
+    pub fn new(navigation: Navigation) -> Self {
+        match navigation {
+            Navigation::Keys => KeyHandler::Keys,
+            Navigation::Modal => KeyHandler::Modal(Modal::default()),
+        }
+    }
+
+    pub fn handle(&mut self, key: &KeyEvent, keymap: &Keymap) -> Vec<Command> {
+        match self {
+            KeyHandler::Keys => keymap.action(key).map(Command::Run).into_iter().collect(),
+            KeyHandler::Modal(modal) => modal.handle(key, keymap),
+        }
+    }
+
+    /// Pasted text goes onto the search or command line if one is open, without its line
+    /// breaks. Anywhere else it is dropped rather than run as keys.
+    pub fn paste(&mut self, text: &str) {
+        if let KeyHandler::Modal(modal) = self {
+            modal.paste(text);
+        }
+    }
+
+    /// What to show at the bottom of the screen while a command is being typed.
+    pub fn prompt(&self) -> Option<String> {
+        match self {
+            KeyHandler::Keys => None,
+            KeyHandler::Modal(modal) => modal.prompt(),
+        }
+    }
+}
+
+/// The largest count a key can be given; more digits are ignored.
+const MAX_COUNT: usize = 99_999;
+
+#[derive(Default)]
+enum Mode {
+    #[default]
+    Normal,
+    /// Selecting the rows from where `V` was pressed to the selected one.
+    Visual,
+    Search(String),
+    CommandLine(String),
+}
+
+/// vi-style keys: `j`/`k` move, `gg`/`G` go to the top and bottom, a count repeats what comes
+/// after it (`5j`), `dd` deletes, `/` searches with `n`/`N` for the next and previous match,
+/// `:` opens a command line and `V` selects lines. Every other key goes through the keymap.
+#[derive(Default)]
+pub struct Modal {
+    mode: Mode,
+    count: Option<usize>,
+    /// The first key of a two-key command, `g` or `d`.
+    pending: Option<char>,
+}
+
+impl Modal {
+    pub fn paste(&mut self, text: &str) {
+        if let Mode::Search(line) | Mode::CommandLine(line) = &mut self.mode {
+            line.extend(text.chars().filter(|c| !c.is_control()));
+        }
+    }
+
+    pub fn handle(&mut self, key: &KeyEvent, keymap: &Keymap) -> Vec<Command> {
+        match &mut self.mode {
+            Mode::Search(text) | Mode::CommandLine(text) => match key.code {
+                KeyCode::Esc => {
+                    self.mode = Mode::Normal;
+                    Vec::new()
+                }
+                KeyCode::Enter => match std::mem::take(&mut self.mode) {
+                    Mode::Search(text) if !text.is_empty() => vec![Command::Search(text)],
+                    Mode::CommandLine(text) => command_line(&text),
+                    _ => Vec::new(),
+                },
+                KeyCode::Backspace => {
+                    if text.pop().is_none() {
+                        self.mode = Mode::Normal;
+                    }
+                    Vec::new()
+                }
+                KeyCode::Char(c) => {
+                    text.push(c);
+                    Vec::new()
+                }
+                _ => Vec::new(),
+            },
+            Mode::Normal | Mode::Visual => self.normal(key, keymap),
+        }
+    }
+
+    fn normal(&mut self, key: &KeyEvent, keymap: &Keymap) -> Vec<Command> {
+        let visual = matches!(self.mode, Mode::Visual);
+        let c = KeyBinding::from(key).plain_char();
+        if let Some(pending) = self.pending.take() {
+            let count = self.count.take().unwrap_or(1);
+            return match (pending, c) {
+                ('g', Some('g')) => vec![Command::GoTo(count)],
+                ('d', Some('d')) => vec![Command::DeleteRows(count)],
+                _ => Vec::new(),
+            };
+        }
+        if let Some(digit) = c.and_then(|c| c.to_digit(10)) {
+            if digit > 0 || self.count.is_some() {
+                let count = self.count.unwrap_or(0) * 10 + digit as usize;
+                if count <= MAX_COUNT {
+                    self.count = Some(count);
+                }
+                return Vec::new();
+            }
+        }
+        let counted = self.count.take();
+        let count = counted.unwrap_or(1);
+        let repeat = |action| (0..count).map(|_| Command::Run(action)).collect();
+        match (c, key.code) {
+            (Some('g'), _) => {
+                self.pending = c;
+                self.count = counted;
+                Vec::new()
+            }
+            (Some('d'), _) if !visual => {
+                self.pending = c;
+                self.count = counted;
+                Vec::new()
+            }
+            (Some('d'), _) => {
+                self.mode = Mode::Normal;
+                vec![Command::Run(Action::Delete), Command::Visual(false)]
+            }
+            (Some('j'), _) => repeat(Action::Down),
+            (Some('k'), _) => repeat(Action::Up),
+            (Some('G'), _) => match counted {
+                Some(row) => vec![Command::GoTo(row)],
+                None => vec![Command::Run(Action::Bottom)],
+            },
+            (Some('n'), _) => repeat(Action::SearchNext),
+            (Some('N'), _) => repeat(Action::SearchPrevious),
+            (Some('/'), _) => {
+                self.mode = Mode::Search(String::new());
+                Vec::new()
+            }
+            (Some(':'), _) => {
+                self.mode = Mode::CommandLine(String::new());
+                Vec::new()
+            }
+            (Some('V'), _) | (_, KeyCode::Esc) if visual => {
+                self.mode = Mode::Normal;
+                vec![Command::Visual(false)]
+            }
+            (Some('V'), _) => {
+                self.mode = Mode::Visual;
+                vec![Command::Visual(true)]
+            }
+            _ => match keymap.action(key) {
+                Some(action) => repeat(action),
+                None => Vec::new(),
+            },
+        }
+    }
+
+    pub fn prompt(&self) -> Option<String> {
+        let pending = self.count.map(|n| n.to_string()).into_iter();
+        let pending: String = pending.chain(self.pending.map(String::from)).collect();
+        match &self.mode {
+            Mode::Search(text) => Some(format!("/{}", text)),
+            Mode::CommandLine(text) => Some(format!(":{}", text)),
+            Mode::Visual => Some(format!("-- VISUAL LINE -- {}", pending)),
+            Mode::Normal if !pending.is_empty() => Some(pending),
+            Mode::Normal => None,
+        }
+    }
+}
+
+/// Reads what was typed after `:`: `w [file.csv]`, `q`, `wq`, `sort[!] [field]` or a row number.
+fn command_line(text: &str) -> Vec<Command> {
+    let mut words = text.split_whitespace();
+    let (name, argument) = (words.next().unwrap_or(""), words.next());
+    match name {
+        "" => Vec::new(),
+        "q" | "q!" | "quit" | "qa" => vec![Command::Run(Action::Quit)],
+        // Every change is already saved, so there is only something to write with a file.
+        "wq" | "x" => argument
+            .map(|file| Command::Write(Some(file.to_owned())))
+            .into_iter()
+            .chain(Some(Command::Run(Action::Quit)))
+            .collect(),
+        "w" | "write" => vec![Command::Write(argument.map(str::to_owned))],
+        "sort" | "sort!" => vec![Command::Sort {
+            by: argument.map(str::to_owned),
+            reverse: name.ends_with('!'),
+        }],
+        _ => match name.parse::<usize>() {
+            Ok(row) => vec![Command::GoTo(row)],
+            Err(_) => vec![Command::Unknown(text.trim().to_owned())],
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::keymap::KeyConfig;
+
+    fn press(modal: &mut Modal, keys: &str) -> Vec<Command> {
+        let keymap = Keymap::new(&KeyConfig::new()).unwrap();
+        keys.chars()
+            .flat_map(|c| modal.handle(&KeyEvent::from(KeyCode::Char(c)), &keymap))
+            .collect()
+    }
+
+    #[test]
+    fn reads_the_command_line() {
+        assert_eq!(command_line(""), []);
+        assert_eq!(command_line("q"), [Command::Run(Action::Quit)]);
+        assert_eq!(command_line("w"), [Command::Write(None)]);
+        assert_eq!(
+            command_line("w pets.csv"),
+            [Command::Write(Some("pets.csv".to_owned()))]
+        );
+        assert_eq!(
+            command_line("sort! name"),
+            [Command::Sort {
+                by: Some("name".to_owned()),
+                reverse: true
+            }]
+        );
+        assert_eq!(command_line("wq"), [Command::Run(Action::Quit)]);
+        assert_eq!(
+            command_line("x pets.csv"),
+            [
+                Command::Write(Some("pets.csv".to_owned())),
+                Command::Run(Action::Quit)
+            ]
+        );
+        assert_eq!(command_line(" 12 "), [Command::GoTo(12)]);
+        assert_eq!(
+            command_line("frobnicate now"),
+            [Command::Unknown("frobnicate now".to_owned())]
+        );
+    }
+
+    #[test]
+    fn a_count_repeats_what_comes_after_it() {
+        let mut modal = Modal::default();
+        assert_eq!(
+            press(&mut modal, "3j"),
+            (0..3)
+                .map(|_| Command::Run(Action::Down))
+                .collect::<Vec<_>>()
+        );
+        assert_eq!(press(&mut modal, "j"), [Command::Run(Action::Down)]);
+        assert_eq!(press(&mut modal, "12G"), [Command::GoTo(12)]);
+        assert_eq!(press(&mut modal, "4gg"), [Command::GoTo(4)]);
+        assert_eq!(press(&mut modal, "gg"), [Command::GoTo(1)]);
+        assert_eq!(press(&mut modal, "2dd"), [Command::DeleteRows(2)]);
+    }
+
+    #[test]
+    fn gg_and_g_move_in_visual_mode_too() {
+        let mut modal = Modal::default();
+        assert_eq!(press(&mut modal, "V"), [Command::Visual(true)]);
+        assert_eq!(press(&mut modal, "gg"), [Command::GoTo(1)]);
+        assert_eq!(press(&mut modal, "3G"), [Command::GoTo(3)]);
+        assert_eq!(
+            press(&mut modal, "d"),
+            [Command::Run(Action::Delete), Command::Visual(false)]
+        );
+    }
+
+    #[test]
+    fn counts_stop_growing_at_the_limit() {
+        let mut modal = Modal::default();
+        press(&mut modal, "123456789");
+        assert_eq!(modal.count, Some(12345));
+        assert_eq!(press(&mut modal, "G"), [Command::GoTo(12345)]);
+        press(&mut modal, &"9".repeat(30));
+        assert!(modal.count.unwrap() <= MAX_COUNT);
+    }
+
+    #[test]
+    fn a_leading_zero_is_not_a_count() {
+        let mut modal = Modal::default();
+        press(&mut modal, "0");
+        assert_eq!(modal.count, None);
+    }
+
+    #[test]
+    fn the_command_line_opens_with_a_colon() {
+        let keymap = Keymap::new(&KeyConfig::new()).unwrap();
+        let mut modal = Modal::default();
+        press(&mut modal, ":w out.csv");
+        assert_eq!(modal.prompt(), Some(":w out.csv".to_owned()));
+        assert_eq!(
+            modal.handle(&KeyEvent::from(KeyCode::Enter), &keymap),
+            [Command::Write(Some("out.csv".to_owned()))]
+        );
+        assert_eq!(modal.prompt(), None);
+    }
+
+    #[test]
+    fn pastes_only_onto_the_command_line() {
+        let mut modal = Modal::default();
+        modal.paste("dd");
+        assert_eq!(modal.prompt(), None);
+        assert_eq!(press(&mut modal, "j"), [Command::Run(Action::Down)]);
+
+        press(&mut modal, ":");
+        modal.paste("w out.csv\nq");
+        assert_eq!(modal.prompt(), Some(":w out.csvq".to_owned()));
+    }
+}
@@ -0,0 +1,177 @@
+use chrono::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::child::Child;
+use crate::db_stuff::{Database, Error, Stored};
+use crate::pet::Pet;
+
+/// What happens to the pets of a child that gets deleted.
+#[derive(Serialize, Deserialize, Copy, Clone, Debug, PartialEq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum OnDelete {
+    /// Refuse to delete a child that still owns pets.
+    #[default]
+    Block,
+    /// Delete the pets along with their owner.
+    Cascade,
+    /// Keep the pets, without an owner.
+    Nullify,
+}
+
+impl Database {
+    /// The owner of `pet`, which may be in the trash.
+    pub fn owner_of(&self, pet: &Pet) -> Option<&Child> {
+        let owner = pet.owner?;
+        self.children.iter().find(|child| child.id == owner)
+    }
+
+    /// The pets owned by the child `child_id`, leaving out the trashed ones.
+    pub fn pets_of(&self, child_id: usize) -> Vec<&Pet> {
+        self.pets
+            .iter()
+            .filter(|pet| pet.owner == Some(child_id) && pet.deleted_at.is_none())
+            .collect()
+    }
+
+    /// Gives the pet `pet_id` to the child `owner`, or to nobody.
+    pub fn set_owner(&mut self, pet_id: usize, owner: Option<usize>) -> Result<(), Error> {
+        if let Some(owner) = owner {
+            let child = Child::position(self, owner).map(|idx| &self.children[idx]);
+            if child.is_none_or(|child| child.deleted_at.is_some()) {
+                return Err(Error::Invalid(format!("there is no child {}", owner)));
+            }
+        }
+        let idx = Pet::position(self, pet_id)
+            .ok_or_else(|| Error::Invalid(format!("there is no pet {}", pet_id)))?;
+        self.pets[idx].owner = owner;
+        Ok(())
+    }
+
+    /// Deals with the pets of the child `child_id` before it goes to the trash or, with `purge`,
+    /// away for good. Trashing only looks at live pets, purging at all of them.
+    pub fn release_child(
+        &mut self,
+        child_id: usize,
+        on_delete: OnDelete,
+        purge: bool,
+    ) -> Result<(), Error> {
+        let owned = |pet: &Pet| pet.owner == Some(child_id) && (purge || pet.deleted_at.is_none());
+        let count = self.pets.iter().filter(|pet| owned(pet)).count();
+        if count == 0 {
+            return Ok(());
+        }
+        match on_delete {
+            OnDelete::Block => {
+                return Err(Error::Invalid(format!(
+                    "child {} still owns {} pet(s), give them another owner first",
+                    child_id, count
+                )))
+            }
+            OnDelete::Cascade if purge => self.pets.retain(|pet| !owned(pet)),
+            OnDelete::Cascade => {
+                let now = Utc::now().round_subsecs(0);
+                for pet in self.pets.iter_mut().filter(|pet| owned(pet)) {
+                    pet.deleted_at = Some(now);
+                }
+            }
+            OnDelete::Nullify => {
+                for pet in self.pets.iter_mut().filter(|pet| owned(pet)) {
+                    pet.owner = None;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Pets 1 and 2 belong to child 10, pet 2 is trashed. Child 11 has no pets and child 12
+    /// is trashed.
+    fn db() -> Database {
+        serde_json::from_value(serde_json::json!({
+            "version": crate::migrations::CURRENT_VERSION,
+            "categories": ["cats"],
+            "pets": [
+                {"id": 1, "name": "Nacho", "category": "cats", "birthdate": "2018-08-20T12:00:00Z",
+                 "created_at": "2020-01-01T00:00:00Z", "owner": 10},
+                {"id": 2, "name": "Tacos", "category": "cats", "birthdate": "2015-03-16T12:00:00Z",
+                 "created_at": "2020-01-01T00:00:00Z", "owner": 10,
+                 "deleted_at": "2021-01-01T00:00:00Z"},
+                {"id": 3, "name": "Mischka", "category": "cats", "birthdate": "2016-11-23T12:00:00Z",
+                 "created_at": "2020-01-01T00:00:00Z"}
+            ],
+            "children": [
+                {"id": 10, "name": "Alex", "birthdate": "2012-05-01T00:00:00Z"},
+                {"id": 11, "name": "Sam", "birthdate": "2014-02-11T00:00:00Z"},
+                {"id": 12, "name": "Kim", "birthdate": "2013-09-30T00:00:00Z",
+                 "deleted_at": "2021-01-01T00:00:00Z"}
+            ]
+        }))
+        .unwrap()
+    }
+
+    fn owners(db: &Database) -> Vec<Option<usize>> {
+        db.pets.iter().map(|pet| pet.owner).collect()
+    }
+
+    #[test]
+    fn gives_pets_only_to_live_children() {
+        let mut db = db();
+        db.set_owner(3, Some(11)).unwrap();
+        db.set_owner(1, None).unwrap();
+        assert!(db.set_owner(3, Some(12)).is_err());
+        assert!(db.set_owner(3, Some(99)).is_err());
+        assert!(db.set_owner(99, Some(11)).is_err());
+        assert_eq!(owners(&db), [None, Some(10), Some(11)]);
+        assert_eq!(db.owner_of(&db.pets[2]).unwrap().name, "Sam");
+        assert_eq!(db.pets_of(10).len(), 0);
+    }
+
+    #[test]
+    fn flags_pets_whose_owner_is_gone() {
+        let mut db = db();
+        db.validate().unwrap();
+        db.children.retain(|child| child.id != 10);
+        assert!(db.pets[0].problem(&db).is_some());
+        assert!(db.pets[2].problem(&db).is_none());
+        assert!(db.validate().is_err());
+    }
+
+    #[test]
+    fn blocks_trashing_a_child_with_live_pets() {
+        let mut db = db();
+        assert!(db.release_child(10, OnDelete::Block, false).is_err());
+        db.release_child(11, OnDelete::Block, false).unwrap();
+        db.pets[0].deleted_at = db.pets[1].deleted_at;
+        db.release_child(10, OnDelete::Block, false).unwrap();
+        assert!(db.release_child(10, OnDelete::Block, true).is_err());
+    }
+
+    #[test]
+    fn cascades_to_live_pets_when_trashing_and_to_all_when_purging() {
+        let mut trashed = db();
+        trashed.release_child(10, OnDelete::Cascade, false).unwrap();
+        assert!(trashed.pets[0].deleted_at.is_some());
+        assert_eq!(trashed.pets[1].deleted_at, db().pets[1].deleted_at);
+        assert!(trashed.pets[2].deleted_at.is_none());
+
+        let mut purged = db();
+        purged.release_child(10, OnDelete::Cascade, true).unwrap();
+        assert_eq!(purged.pets.len(), 1);
+        assert_eq!(purged.pets[0].id, 3);
+    }
+
+    #[test]
+    fn nullifies_live_pets_when_trashing_and_all_when_purging() {
+        let mut trashed = db();
+        trashed.release_child(10, OnDelete::Nullify, false).unwrap();
+        assert_eq!(owners(&trashed), [None, Some(10), None]);
+
+        let mut purged = db();
+        purged.release_child(10, OnDelete::Nullify, true).unwrap();
+        assert_eq!(owners(&purged), [None, None, None]);
+    }
+}
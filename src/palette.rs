@@ -0,0 +1,184 @@
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use tui::{
+    text::{Span, Spans},
+    widgets::{Block, BorderType, Borders, List, ListItem, ListState, Paragraph},
+};
+
+use crate::keymap::Action;
+use crate::theme::{Role, Theme};
+
+/// One action in the palette, with what it does and the keys it is bound to.
+pub struct Entry {
+    pub action: Action,
+    pub description: String,
+    pub keys: String,
+}
+
+/// Every action, narrowed down by typing part of its name or description.
+pub struct Palette {
+    query: String,
+    entries: Vec<Entry>,
+    /// The entries matching `query`, best match first.
+    matches: Vec<usize>,
+    list_state: ListState,
+}
+
+/// What a key press did to the palette.
+pub enum Chosen {
+    Searching,
+    Run(Action),
+    Cancelled,
+}
+
+impl Palette {
+    pub fn new(entries: Vec<Entry>) -> Self {
+        let mut palette = Palette {
+            query: String::new(),
+            matches: Vec::new(),
+            entries,
+            list_state: ListState::default(),
+        };
+        palette.narrow();
+        palette
+    }
+
+    pub fn handle(&mut self, key: &KeyEvent) -> Chosen {
+        let len = self.matches.len();
+        let selected = self.list_state.selected();
+        match key.code {
+            KeyCode::Esc => return Chosen::Cancelled,
+            KeyCode::Enter => {
+                if let Some(&entry) = selected.and_then(|idx| self.matches.get(idx)) {
+                    return Chosen::Run(self.entries[entry].action);
+                }
+            }
+            KeyCode::Down if len > 0 => {
+                self.list_state.select(selected.map(|idx| (idx + 1) % len));
+            }
+            KeyCode::Up if len > 0 => {
+                self.list_state
+                    .select(selected.map(|idx| (idx + len - 1) % len));
+            }
+            KeyCode::Backspace => {
+                self.query.pop();
+                self.narrow();
+            }
+            KeyCode::Char(c) if !key.modifiers.contains(KeyModifiers::CONTROL) => {
+                self.query.push(c);
+                self.narrow();
+            }
+            _ => {}
+        }
+        Chosen::Searching
+    }
+
+    pub fn paste(&mut self, text: &str) {
+        self.query.extend(text.chars().filter(|c| !c.is_control()));
+        self.narrow();
+    }
+
+    fn narrow(&mut self) {
+        let mut scored: Vec<(i64, usize)> = self
+            .entries
+            .iter()
+            .enumerate()
+            .filter_map(|(idx, entry)| {
+                let text = format!("{} {}", entry.action, entry.description);
+                fuzzy_score(&self.query, &text).map(|score| (-score, idx))
+            })
+            .collect();
+        scored.sort();
+        self.matches = scored.into_iter().map(|(_, idx)| idx).collect();
+        self.list_state.select(match self.matches.len() {
+            0 => None,
+            _ => Some(0),
+        });
+    }
+
+    pub fn list_state(&self) -> &ListState {
+        &self.list_state
+    }
+
+    pub fn render(&self, theme: &Theme) -> (Paragraph<'_>, List<'_>) {
+        let input = Paragraph::new(Spans::from(vec![
+            Span::raw("> "),
+            Span::raw(self.query.as_str()),
+            Span::styled("_", theme.style(Role::Muted)),
+        ]))
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .style(theme.style(Role::Accent))
+                .title("Actions (type to search, Enter runs, Esc cancels)")
+                .border_type(BorderType::Plain),
+        );
+        let items: Vec<_> = self
+            .matches
+            .iter()
+            .map(|&idx| {
+                let entry = &self.entries[idx];
+                ListItem::new(Spans::from(vec![
+                    Span::raw(format!("{:<16}", entry.action.to_string())),
+                    Span::raw(format!("{:<44}", entry.description)),
+                    Span::styled(entry.keys.as_str(), theme.style(Role::Accent)),
+                ]))
+            })
+            .collect();
+        let list = List::new(items)
+            .block(
+                Block::default()
+                    .borders(Borders::ALL)
+                    .style(theme.style(Role::Accent))
+                    .border_type(BorderType::Plain),
+            )
+            .highlight_style(theme.highlight());
+        (input, list)
+    }
+}
+
+/// How well `query` matches `text` when its characters appear in `text` in the same order,
+/// ignoring case and spaces; higher is better. Runs of adjacent characters and characters at
+/// the start of a word count extra, gaps count against it. `None` if it doesn't match at all.
+fn fuzzy_score(query: &str, text: &str) -> Option<i64> {
+    let text: Vec<char> = text.to_lowercase().chars().collect();
+    let mut score = 0;
+    let mut next = 0;
+    let mut previous: Option<usize> = None;
+    for c in query.to_lowercase().chars().filter(|c| !c.is_whitespace()) {
+        let found = (next..text.len()).find(|&idx| text[idx] == c)?;
+        score += 1;
+        if found > 0 && previous == Some(found - 1) {
+            score += 5;
+        }
+        if found == 0 || !text[found - 1].is_alphanumeric() {
+            score += 3;
+        }
+        score -= (found - next).min(10) as i64;
+        previous = Some(found);
+        next = found + 1;
+    }
+    Some(score)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn matches_characters_in_order() {
+        assert!(fuzzy_score("pts", "pets").is_some());
+        assert!(fuzzy_score("PETS", "the pets").is_some());
+        assert!(fuzzy_score("th pe", "the pets").is_some());
+        assert_eq!(fuzzy_score("sp", "pets"), None);
+        assert_eq!(fuzzy_score("x", "pets"), None);
+        assert_eq!(fuzzy_score("", "pets"), Some(0));
+    }
+
+    #[test]
+    fn prefers_runs_and_word_starts() {
+        let score = |query| fuzzy_score(query, "sort the pets").unwrap();
+        assert!(score("pet") > score("pts"));
+        assert!(score("so") > score("or"));
+        assert!(fuzzy_score("t", "a t") > fuzzy_score("t", "at"));
+    }
+}
@@ -1,31 +1,65 @@
 use chrono::prelude::*;
 use rand::{distributions::Alphanumeric, prelude::*};
 use serde::{Deserialize, Serialize};
-use tui::{
-    layout::Constraint,
-    style::{Color, Modifier, Style},
-    text::{Span, Spans},
-    widgets::{Block, BorderType, Borders, Cell, Row, Table},
-};
 
-use crate::data_row::{Aged, DataRow};
+use crate::category::{Category, DEFAULT_CATEGORIES};
+use crate::data_row::{local_time, AgeUnit, Born, DataRow, Field, FieldKind};
+use crate::db_stuff::Database;
 
 #[derive(Serialize, Deserialize, Clone)]
 pub struct Pet {
     pub id: usize,
     pub name: String,
-    pub category: String,
-    pub age: u8,
+    pub category: Category,
+    pub birthdate: DateTime<Utc>,
     pub created_at: DateTime<Utc>,
+    /// The id of the child that owns the pet.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub owner: Option<usize>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub deleted_at: Option<DateTime<Utc>>,
 }
 
+const PET_FIELDS: &[Field] = &[
+    Field {
+        name: "id",
+        label: "ID",
+        kind: FieldKind::Integer,
+    },
+    Field {
+        name: "name",
+        label: "Name",
+        kind: FieldKind::Text,
+    },
+    Field {
+        name: "category",
+        label: "Category",
+        kind: FieldKind::Text,
+    },
+    Field {
+        name: "birthdate",
+        label: "Birthdate",
+        kind: FieldKind::Timestamp,
+    },
+    Field {
+        name: "created_at",
+        label: "Created At",
+        kind: FieldKind::Timestamp,
+    },
+    Field {
+        name: "owner",
+        label: "Owner",
+        kind: FieldKind::Reference,
+    },
+];
+
 impl DataRow for Pet {
-    fn name(&self) -> String {
-        self.name.clone()
+    fn id(&self) -> usize {
+        self.id
     }
 
-    fn menu_titles() -> Vec<&'static str> {
-        vec!["Home", "Pets", "Add", "Edit Name", "Delete", "Quit"]
+    fn name(&self) -> String {
+        self.name.clone()
     }
 
     fn title() -> &'static str {
@@ -40,92 +74,69 @@ impl DataRow for Pet {
         "pet CLI".to_string()
     }
 
-    fn help_part<'a>() -> Vec<Spans<'a>> {
+    fn detail(&self, db: &Database, unit: AgeUnit) -> Vec<(&'static str, String)> {
+        let owner = match db.owner_of(self) {
+            Some(child) => format!("{} ({})", child.name, child.id),
+            None => "-".to_owned(),
+        };
         vec![
-            Spans::from(vec![Span::raw(
-                "Press 'p' to access pets, 'a' to add random new pets,",
-            )]),
-            Spans::from(vec![Span::raw(
-                "'e' to edit the name of currently selected pet",
-            )]),
-            Spans::from(vec![Span::raw(
-                "and 'd' to delete the currently selected pet.",
-            )]),
+            ("ID", self.id.to_string()),
+            ("Name", self.name.clone()),
+            ("Category", self.category.to_string()),
+            ("Age", self.age_text(unit)),
+            ("Birthdate", local_time(&self.birthdate)),
+            ("Created At", local_time(&self.created_at)),
+            ("Owner", owner),
         ]
     }
 
-    fn to_table(&self) -> Table {
-        Table::new(vec![Row::new(vec![
-            Cell::from(Span::raw(self.id.to_string())),
-            Cell::from(Span::raw(self.name.to_string())),
-            Cell::from(Span::raw(self.category.to_string())),
-            Cell::from(Span::raw(self.age.to_string())),
-            Cell::from(Span::raw(self.created_at.to_string())),
-        ])])
-        .header(Row::new(vec![
-            Cell::from(Span::styled(
-                "ID",
-                Style::default().add_modifier(Modifier::BOLD),
-            )),
-            Cell::from(Span::styled(
-                "Name",
-                Style::default().add_modifier(Modifier::BOLD),
-            )),
-            Cell::from(Span::styled(
-                "Category",
-                Style::default().add_modifier(Modifier::BOLD),
-            )),
-            Cell::from(Span::styled(
-                "Age",
-                Style::default().add_modifier(Modifier::BOLD),
-            )),
-            Cell::from(Span::styled(
-                "Created At",
-                Style::default().add_modifier(Modifier::BOLD),
-            )),
-        ]))
-        .block(
-            Block::default()
-                .borders(Borders::ALL)
-                .style(Style::default().fg(Color::White))
-                .title("Detail")
-                .border_type(BorderType::Plain),
-        )
-        .widths(&[
-            Constraint::Percentage(5),
-            Constraint::Percentage(20),
-            Constraint::Percentage(20),
-            Constraint::Percentage(5),
-            Constraint::Percentage(20),
-        ])
-    }
-
     fn create_placeholder() -> Self {
         let mut rng = rand::thread_rng();
-        let catsdogs = match rng.gen_range(0, 1) {
-            0 => "cats",
-            _ => "dogs",
-        };
+        let catsdogs = DEFAULT_CATEGORIES[rng.gen_range(0, DEFAULT_CATEGORIES.len())];
         Self {
             id: rng.gen_range(0, 9999999),
             name: rng.sample_iter(Alphanumeric).take(10).collect(),
-            category: catsdogs.to_owned(),
-            age: rng.gen_range(1, 15),
+            category: Category::new(catsdogs).expect("default categories are valid"),
+            birthdate: AgeUnit::Years
+                .shift(Utc::now().round_subsecs(0), -rng.gen_range(1, 15))
+                .expect("recent dates can be shifted"),
             created_at: Utc::now().round_subsecs(0),
+            owner: None,
+            deleted_at: None,
         }
     }
 
     fn set_name<T: Into<String>>(&mut self, new_name: T) {
         self.name = new_name.into();
     }
+
+    fn fields() -> &'static [Field] {
+        PET_FIELDS
+    }
+
+    fn calendar_field() -> &'static str {
+        "created_at"
+    }
+
+    fn timestamp(&self, field: &str) -> Option<DateTime<Utc>> {
+        match field {
+            "birthdate" => Some(self.birthdate),
+            "created_at" => Some(self.created_at),
+            _ => None,
+        }
+    }
+
+    fn group(&self) -> Option<String> {
+        Some(self.category.to_string())
+    }
 }
 
-impl Aged for Pet {
-    fn age(&self) -> u8 {
-        self.age
+impl Born for Pet {
+    fn birthdate(&self) -> DateTime<Utc> {
+        self.birthdate
     }
 
-    fn set_age<T: Into<u8>>(&mut self, new_age: T) {
-        self.age = new_age.into();
+    fn set_birthdate(&mut self, birthdate: DateTime<Utc>) {
+        self.birthdate = birthdate;
     }
 }
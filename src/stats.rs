@@ -0,0 +1,62 @@
+use std::collections::BTreeMap;
+
+use crate::data_row::{AgeUnit, Aged, DataRow};
+use chrono::prelude::*;
+
+/// How many months the per-month counts go back, this month included.
+pub const MONTHS: usize = 24;
+
+/// Figures about a set of rows, for the Stats screen.
+pub struct Stats {
+    pub count: usize,
+    /// How many rows are in each group, see `DataRow::group`. Empty if the rows have no groups.
+    pub per_group: Vec<(String, u64)>,
+    /// How many rows have each age, for the ages that some row has, youngest first. Ages
+    /// counted in days spread far, so the ages in between are left out.
+    pub per_age: Vec<(u32, u64)>,
+    /// How many rows fall into each of the last `MONTHS` months by their calendar field,
+    /// oldest month first.
+    pub per_month: Vec<u64>,
+    pub average_age: Option<f64>,
+}
+
+impl Stats {
+    /// Ages are counted in `unit`s.
+    pub fn of<T: DataRow + Aged>(rows: &[T], today: NaiveDate, unit: AgeUnit) -> Self {
+        let mut per_group = BTreeMap::new();
+        for group in rows.iter().filter_map(DataRow::group) {
+            *per_group.entry(group).or_insert(0) += 1;
+        }
+
+        let ages: Vec<u32> = rows.iter().map(|row| row.age(unit)).collect();
+        let mut per_age = BTreeMap::new();
+        for &age in &ages {
+            *per_age.entry(age).or_insert(0) += 1;
+        }
+        let average_age = match ages.len() {
+            0 => None,
+            n => Some(ages.iter().map(|&age| f64::from(age)).sum::<f64>() / n as f64),
+        };
+
+        let month_index = |year: i32, month: u32| year * 12 + month as i32;
+        let this_month = month_index(today.year(), today.month());
+        let mut per_month = vec![0; MONTHS];
+        for at in rows
+            .iter()
+            .filter_map(|row| row.timestamp(T::calendar_field()))
+        {
+            let back = this_month - month_index(at.year(), at.month());
+            if (0..MONTHS as i32).contains(&back) {
+                per_month[MONTHS - 1 - back as usize] += 1;
+            }
+        }
+
+        Stats {
+            count: rows.len(),
+            per_group: per_group.into_iter().collect(),
+            per_age: per_age.into_iter().collect(),
+            per_month,
+            average_age,
+        }
+    }
+}
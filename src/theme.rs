@@ -0,0 +1,313 @@
+use std::collections::BTreeMap;
+use std::fmt;
+use std::str::FromStr;
+
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+use tui::style::{Color, Modifier, Style};
+
+use crate::db_stuff::Error;
+
+/// What a color is used for. Widgets ask the theme for a role instead of naming colors.
+#[derive(Serialize, Deserialize, Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "kebab-case")]
+pub enum Role {
+    /// Text and borders.
+    Text,
+    /// Keys, popups and bars: what should catch the eye.
+    Accent,
+    /// Secondary figures, like counts and timestamps.
+    Info,
+    /// The name of the program on the Home screen.
+    Title,
+    Error,
+    /// Things that are there but don't matter much, like the days of other months.
+    Muted,
+    /// The background of the selected entry.
+    Selection,
+    /// The text of the selected entry.
+    SelectionText,
+    /// The background of rows in a range selection.
+    Marked,
+    /// Lines that would be added by restoring a backup.
+    Added,
+    /// Lines that would be removed by restoring a backup.
+    Removed,
+    /// The selection while picking a category to merge into.
+    Merging,
+}
+
+/// The built-in themes; `[theme.colors]` in the config can change single roles of them.
+#[derive(Serialize, Deserialize, Copy, Clone, Debug, PartialEq, Default)]
+#[serde(rename_all = "kebab-case")]
+pub enum Preset {
+    #[default]
+    Dark,
+    Light,
+    HighContrast,
+    /// No colors at all, only bold, underlined and reversed text.
+    Monochrome,
+}
+
+impl Preset {
+    pub const ALL: &'static [Preset] = &[
+        Preset::Dark,
+        Preset::Light,
+        Preset::HighContrast,
+        Preset::Monochrome,
+    ];
+
+    fn colors(self) -> [Color; 12] {
+        // In the order of `ROLES`.
+        match self {
+            Preset::Dark => [
+                Color::White,
+                Color::Yellow,
+                Color::LightCyan,
+                Color::LightBlue,
+                Color::LightRed,
+                Color::DarkGray,
+                Color::Yellow,
+                Color::Black,
+                Color::DarkGray,
+                Color::Green,
+                Color::Red,
+                Color::LightGreen,
+            ],
+            Preset::Light => [
+                Color::Black,
+                Color::Blue,
+                Color::Magenta,
+                Color::Blue,
+                Color::Red,
+                Color::Gray,
+                Color::Blue,
+                Color::White,
+                Color::Gray,
+                Color::Green,
+                Color::Red,
+                Color::Green,
+            ],
+            Preset::HighContrast => [
+                Color::White,
+                Color::LightYellow,
+                Color::LightCyan,
+                Color::White,
+                Color::LightRed,
+                Color::Gray,
+                Color::White,
+                Color::Black,
+                Color::Blue,
+                Color::LightGreen,
+                Color::LightRed,
+                Color::LightGreen,
+            ],
+            Preset::Monochrome => [Color::Reset; 12],
+        }
+    }
+}
+
+impl fmt::Display for Preset {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match serde_json::to_value(self) {
+            Ok(serde_json::Value::String(name)) => f.write_str(&name),
+            _ => write!(f, "{:?}", self),
+        }
+    }
+}
+
+const ROLES: [Role; 12] = [
+    Role::Text,
+    Role::Accent,
+    Role::Info,
+    Role::Title,
+    Role::Error,
+    Role::Muted,
+    Role::Selection,
+    Role::SelectionText,
+    Role::Marked,
+    Role::Added,
+    Role::Removed,
+    Role::Merging,
+];
+
+/// A color in the config: a name like `light-cyan`, `#rrggbb`, a number from the 256-color
+/// palette, or `reset` for the terminal's own color.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct ThemeColor(Color);
+
+const COLOR_NAMES: &[(&str, Color)] = &[
+    ("reset", Color::Reset),
+    ("black", Color::Black),
+    ("red", Color::Red),
+    ("green", Color::Green),
+    ("yellow", Color::Yellow),
+    ("blue", Color::Blue),
+    ("magenta", Color::Magenta),
+    ("cyan", Color::Cyan),
+    ("gray", Color::Gray),
+    ("dark-gray", Color::DarkGray),
+    ("light-red", Color::LightRed),
+    ("light-green", Color::LightGreen),
+    ("light-yellow", Color::LightYellow),
+    ("light-blue", Color::LightBlue),
+    ("light-magenta", Color::LightMagenta),
+    ("light-cyan", Color::LightCyan),
+    ("white", Color::White),
+];
+
+impl FromStr for ThemeColor {
+    type Err = Error;
+
+    fn from_str(input: &str) -> Result<Self, Error> {
+        let name = input.trim().to_lowercase().replace(['_', ' '], "-");
+        if let Some((_, color)) = COLOR_NAMES
+            .iter()
+            .find(|(known, _)| *known == name || known.replace('-', "") == name)
+        {
+            return Ok(ThemeColor(*color));
+        }
+        if let Some(hex) = name.strip_prefix('#').filter(|hex| hex.len() == 6) {
+            if let Ok(rgb) = u32::from_str_radix(hex, 16) {
+                let [_, r, g, b] = rgb.to_be_bytes();
+                return Ok(ThemeColor(Color::Rgb(r, g, b)));
+            }
+        }
+        match name.parse::<u8>() {
+            Ok(index) => Ok(ThemeColor(Color::Indexed(index))),
+            Err(_) => Err(Error::Invalid(format!(
+                "'{}' is not a color, use a name like light-cyan, #rrggbb or 0 to 255",
+                input
+            ))),
+        }
+    }
+}
+
+impl fmt::Display for ThemeColor {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.0 {
+            Color::Rgb(r, g, b) => write!(f, "#{:02x}{:02x}{:02x}", r, g, b),
+            Color::Indexed(index) => write!(f, "{}", index),
+            color => match COLOR_NAMES.iter().find(|(_, known)| *known == color) {
+                Some((name, _)) => f.write_str(name),
+                None => write!(f, "{:?}", color),
+            },
+        }
+    }
+}
+
+impl Serialize for ThemeColor {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for ThemeColor {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let input = String::deserialize(deserializer)?;
+        input.parse().map_err(de::Error::custom)
+    }
+}
+
+/// The `[theme]` section of the config.
+#[derive(Serialize, Deserialize, Clone, Default)]
+#[serde(default)]
+pub struct ThemeConfig {
+    pub preset: Preset,
+    /// Colors that differ from the preset's, by role.
+    pub colors: BTreeMap<Role, ThemeColor>,
+}
+
+impl ThemeConfig {
+    /// Drops every color when the `NO_COLOR` environment variable is set, see no-color.org.
+    pub fn apply_no_color(&mut self) {
+        if std::env::var_os("NO_COLOR").is_some_and(|value| !value.is_empty()) {
+            self.preset = Preset::Monochrome;
+            self.colors.clear();
+        }
+    }
+}
+
+/// The colors the TUI is drawn in.
+pub struct Theme {
+    colors: BTreeMap<Role, Color>,
+}
+
+impl Theme {
+    pub fn new(config: &ThemeConfig) -> Self {
+        let mut colors: BTreeMap<Role, Color> = ROLES
+            .iter()
+            .copied()
+            .zip(config.preset.colors().iter().copied())
+            .collect();
+        for (role, ThemeColor(color)) in &config.colors {
+            colors.insert(*role, *color);
+        }
+        Theme { colors }
+    }
+
+    pub fn color(&self, role: Role) -> Color {
+        self.colors[&role]
+    }
+
+    /// Text in the color of `role`.
+    pub fn style(&self, role: Role) -> Style {
+        Style::default().fg(self.color(role))
+    }
+
+    /// The selected entry of a list.
+    pub fn highlight(&self) -> Style {
+        self.highlight_on(Role::Selection)
+    }
+
+    /// The selected entry of a list, on the color of `role`. Without colors it is reversed.
+    pub fn highlight_on(&self, role: Role) -> Style {
+        let (bg, fg) = (self.color(role), self.color(Role::SelectionText));
+        let style = Style::default().bg(bg).fg(fg).add_modifier(Modifier::BOLD);
+        match (bg, fg) {
+            (Color::Reset, Color::Reset) => style.add_modifier(Modifier::REVERSED),
+            _ => style,
+        }
+    }
+
+    /// Rows in a range selection. Without colors they are underlined.
+    pub fn marked(&self) -> Style {
+        match self.color(Role::Marked) {
+            Color::Reset => Style::default().add_modifier(Modifier::UNDERLINED),
+            color => Style::default().bg(color),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn color(input: &str) -> Color {
+        input.parse::<ThemeColor>().unwrap().0
+    }
+
+    #[test]
+    fn reads_names_hex_and_palette_numbers() {
+        assert_eq!(color("light-cyan"), Color::LightCyan);
+        assert_eq!(color("LightCyan"), Color::LightCyan);
+        assert_eq!(color("light_cyan"), Color::LightCyan);
+        assert_eq!(color(" reset "), Color::Reset);
+        assert_eq!(color("#ff8000"), Color::Rgb(255, 128, 0));
+        assert_eq!(color("#FF8000"), Color::Rgb(255, 128, 0));
+        assert_eq!(color("208"), Color::Indexed(208));
+    }
+
+    #[test]
+    fn refuses_what_is_not_a_color() {
+        for input in ["", "nope", "256", "-1", "#12345", "#1234567", "#gggggg"] {
+            assert!(input.parse::<ThemeColor>().is_err(), "{}", input);
+        }
+    }
+
+    #[test]
+    fn writes_what_it_reads() {
+        for input in ["dark-gray", "#0a0b0c", "17"] {
+            assert_eq!(input.parse::<ThemeColor>().unwrap().to_string(), input);
+        }
+    }
+}
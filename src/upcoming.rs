@@ -0,0 +1,218 @@
+use std::fmt;
+use std::fs;
+use std::path::Path;
+
+use chrono::prelude::*;
+
+use crate::child::Child;
+use crate::db_stuff::{Database, Entity, Error, Stored};
+use crate::pet::Pet;
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Occasion {
+    Birthday,
+    /// Of the day the pet was added, which is when it was adopted.
+    Adoption,
+}
+
+/// A birthday or adoption anniversary coming up.
+pub struct Reminder {
+    pub date: NaiveDate,
+    pub occasion: Occasion,
+    /// How many years it will be on `date`.
+    pub years: i32,
+    pub entity: Entity,
+    pub id: usize,
+    pub name: String,
+}
+
+impl fmt::Display for Reminder {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.occasion {
+            Occasion::Birthday => write!(f, "{} turns {}", self.name, self.years),
+            Occasion::Adoption => write!(f, "{} was adopted {} year(s) ago", self.name, self.years),
+        }
+    }
+}
+
+/// The birthdays of live pets and children and the adoption anniversaries of live pets that
+/// fall within `days` days from `today` (today included), soonest first.
+pub fn upcoming(db: &Database, today: NaiveDate, days: u32) -> Vec<Reminder> {
+    let mut reminders = Vec::new();
+    let mut add = |origin: DateTime<Utc>, occasion, entity, id, name: &str| {
+        let (date, years) = next_anniversary(origin.date_naive(), today);
+        if years > 0 && (date - today).num_days() < i64::from(days) {
+            reminders.push(Reminder {
+                date,
+                occasion,
+                years,
+                entity,
+                id,
+                name: name.to_owned(),
+            });
+        }
+    };
+    for pet in Pet::live(db) {
+        add(
+            pet.birthdate,
+            Occasion::Birthday,
+            Entity::Pets,
+            pet.id,
+            &pet.name,
+        );
+        add(
+            pet.created_at,
+            Occasion::Adoption,
+            Entity::Pets,
+            pet.id,
+            &pet.name,
+        );
+    }
+    for child in Child::live(db) {
+        add(
+            child.birthdate,
+            Occasion::Birthday,
+            Entity::Children,
+            child.id,
+            &child.name,
+        );
+    }
+    reminders.sort_by(|a, b| (a.date, &a.name).cmp(&(b.date, &b.name)));
+    reminders
+}
+
+/// The first anniversary of `origin` on or after `today`, and how many years it marks.
+/// Anniversaries of February 29 fall on February 28 in other years.
+fn next_anniversary(origin: NaiveDate, today: NaiveDate) -> (NaiveDate, i32) {
+    let in_year = |year| {
+        NaiveDate::from_ymd_opt(year, origin.month(), origin.day())
+            .or_else(|| NaiveDate::from_ymd_opt(year, 2, 28))
+            .expect("February 28 exists every year")
+    };
+    let this_year = in_year(today.year());
+    let date = if this_year < today {
+        in_year(today.year() + 1)
+    } else {
+        this_year
+    };
+    (date, date.year() - origin.year())
+}
+
+/// Writes the reminders as all-day events of an iCalendar file, for importing into a calendar.
+pub fn write_ics<P: AsRef<Path>>(path: P, reminders: &[Reminder]) -> Result<(), Error> {
+    let stamp = Utc::now().format("%Y%m%dT%H%M%SZ");
+    let mut lines = vec![
+        "BEGIN:VCALENDAR".to_owned(),
+        "VERSION:2.0".to_owned(),
+        "PRODID:-//rust-commandline-example//upcoming//EN".to_owned(),
+    ];
+    for reminder in reminders {
+        let day = reminder.date.format("%Y%m%d");
+        let next_day = reminder
+            .date
+            .succ_opt()
+            .unwrap_or(reminder.date)
+            .format("%Y%m%d");
+        lines.extend(vec![
+            "BEGIN:VEVENT".to_owned(),
+            format!(
+                "UID:{}-{}-{:?}-{}@rust-commandline-example",
+                reminder.entity,
+                reminder.id,
+                reminder.occasion,
+                reminder.date.year()
+            ),
+            format!("DTSTAMP:{}", stamp),
+            format!("DTSTART;VALUE=DATE:{}", day),
+            format!("DTEND;VALUE=DATE:{}", next_day),
+            format!("SUMMARY:{}", escape_text(&reminder.to_string())),
+            "END:VEVENT".to_owned(),
+        ]);
+    }
+    lines.push("END:VCALENDAR".to_owned());
+    // RFC 5545 wants CRLF line endings.
+    fs::write(path, lines.join("\r\n") + "\r\n")?;
+    Ok(())
+}
+
+fn escape_text(text: &str) -> String {
+    text.replace('\\', "\\\\")
+        .replace(';', "\\;")
+        .replace(',', "\\,")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn db() -> Database {
+        serde_json::from_value(serde_json::json!({
+            "version": crate::migrations::CURRENT_VERSION,
+            "categories": ["cats"],
+            "pets": [
+                {"id": 1, "name": "Nacho", "category": "cats", "birthdate": "2016-02-29T12:00:00Z",
+                 "created_at": "2020-03-10T23:30:00Z"},
+                {"id": 2, "name": "Tacos", "category": "cats", "birthdate": "2015-03-01T00:00:00Z",
+                 "created_at": "2023-03-05T00:00:00Z", "deleted_at": "2023-03-06T00:00:00Z"}
+            ],
+            "children": [
+                {"id": 10, "name": "Alex", "birthdate": "2012-03-05T00:00:00Z"},
+                {"id": 11, "name": "Sam", "birthdate": "2023-02-28T00:00:00Z"}
+            ]
+        }))
+        .unwrap()
+    }
+
+    fn day(text: &str) -> NaiveDate {
+        text.parse().unwrap()
+    }
+
+    fn listed(reminders: &[Reminder]) -> Vec<String> {
+        reminders
+            .iter()
+            .map(|r| format!("{} {}", r.date, r))
+            .collect()
+    }
+
+    #[test]
+    fn lists_live_anniversaries_within_the_window_soonest_first() {
+        let reminders = upcoming(&db(), day("2023-02-28"), 11);
+        assert_eq!(
+            listed(&reminders),
+            [
+                "2023-02-28 Nacho turns 7",
+                "2023-03-05 Alex turns 11",
+                "2023-03-10 Nacho was adopted 3 year(s) ago",
+            ]
+        );
+        assert_eq!(reminders[1].entity, Entity::Children);
+        assert_eq!(reminders[2].occasion, Occasion::Adoption);
+    }
+
+    #[test]
+    fn leaves_out_what_lies_beyond_the_window_or_has_not_had_a_year_yet() {
+        assert_eq!(upcoming(&db(), day("2023-02-28"), 10).len(), 2);
+        assert!(upcoming(&db(), day("2023-02-28"), 0).is_empty());
+        assert_eq!(
+            listed(&upcoming(&db(), day("2024-02-28"), 1)),
+            ["2024-02-28 Sam turns 1"]
+        );
+    }
+
+    #[test]
+    fn moves_leap_day_anniversaries_to_february_28() {
+        let origin = day("2016-02-29");
+        assert_eq!(
+            next_anniversary(origin, day("2023-01-01")),
+            (day("2023-02-28"), 7)
+        );
+        assert_eq!(
+            next_anniversary(origin, day("2024-02-29")),
+            (day("2024-02-29"), 8)
+        );
+        assert_eq!(
+            next_anniversary(origin, day("2023-03-01")),
+            (day("2024-02-29"), 8)
+        );
+    }
+}